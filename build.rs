@@ -0,0 +1,18 @@
+use std::path::Path;
+
+/// Generated by `just css` and gitignored, but embedded into the binary by `assets.rs`
+const GENERATED_ASSETS: &[(&str, &str)] = &[
+    ("static/style.css", "pages will be unstyled"),
+    ("static/fonts", "pages will fall back to system fonts"),
+];
+
+fn main() {
+    for (asset, consequence) in GENERATED_ASSETS {
+        println!("cargo:rerun-if-changed={asset}");
+        if !Path::new(asset).exists() {
+            println!(
+                "cargo:warning={asset} is missing, so {consequence}; run `bun install` and `just css` before building"
+            );
+        }
+    }
+}
@@ -1,9 +1,16 @@
-use axum::{Extension, extract::Query, response::IntoResponse};
+use axum::{
+    Extension,
+    extract::{Path, Query},
+    http::StatusCode,
+    response::IntoResponse,
+};
 use serde::Deserialize;
 use serde_json::json;
 use std::sync::Arc;
 
-use crate::{AppState, error::VestaError, response::jsend};
+use crate::{
+    AppState, auth::Identity, error::VestaError, render::RenderContext, response::jsend, search,
+};
 
 #[derive(Deserialize)]
 pub struct ServiceQuery {
@@ -11,6 +18,24 @@ pub struct ServiceQuery {
     pub title: String,
 }
 
+#[derive(Deserialize)]
+pub struct ActionQuery {
+    pub group: String,
+    pub title: String,
+    pub action: String,
+}
+
+#[derive(Deserialize)]
+pub struct ServicesQuery {
+    pub tag: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct SearchQuery {
+    #[serde(default)]
+    pub q: String,
+}
+
 #[derive(Deserialize)]
 pub struct HealthQuery {
     #[serde(default)]
@@ -21,20 +46,72 @@ pub struct HealthQuery {
 pub async fn get_service(
     Query(query): Query<ServiceQuery>,
     Extension(state): Extension<Arc<AppState>>,
+    Extension(identity): Extension<Identity>,
 ) -> Result<impl IntoResponse, VestaError> {
-    let service = state
+    let mut service = state
         .get_config_manager()
-        .get_service(&query.group, &query.title)?
+        .get_visible_service(&query.group, &query.title, &identity)?
         .ok_or_else(|| VestaError::ServiceNotFound {
             group: query.group.clone(),
             title: query.title.clone(),
         })?;
+    if !identity.is_admin() {
+        service.actions.clear();
+    }
 
     Ok(jsend::success(json!({
         "service": service
     })))
 }
 
+/// Run one of a service's actions, e.g. wake it with Wake-on-LAN
+pub async fn run_service_action(
+    Query(query): Query<ActionQuery>,
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(identity): Extension<Identity>,
+) -> Result<impl IntoResponse, VestaError> {
+    let config = state.get_config()?;
+    let service = config
+        .get_visible_service(&query.group, &query.title, &identity)
+        .ok_or_else(|| VestaError::ServiceNotFound {
+            group: query.group.clone(),
+            title: query.title.clone(),
+        })?;
+    let action = service
+        .actions
+        .iter()
+        .find(|action| action.name == query.action)
+        .ok_or_else(|| VestaError::ApiError {
+            status: StatusCode::NOT_FOUND,
+            message: format!("Action '{}' not found", query.action),
+        })?;
+
+    let result = state
+        .get_action_service()
+        .run(action, &config.commands)
+        .await;
+    match &result {
+        Ok(message) => tracing::info!(
+            user = %identity.username,
+            group = %query.group,
+            service = %service.title,
+            action = %action.name,
+            result = %message,
+            "Service action run"
+        ),
+        Err(e) => tracing::warn!(
+            user = %identity.username,
+            group = %query.group,
+            service = %service.title,
+            action = %action.name,
+            error = %e,
+            "Service action failed"
+        ),
+    }
+
+    Ok(jsend::success_message(result?))
+}
+
 /// Get widget information  
 pub async fn get_widget(
     Query(query): Query<ServiceQuery>,
@@ -53,6 +130,13 @@ pub async fn get_widget(
     })))
 }
 
+/// Get the identity of the current user
+pub async fn whoami(Extension(identity): Extension<Identity>) -> impl IntoResponse {
+    jsend::success(json!({
+        "identity": identity
+    }))
+}
+
 /// Get application health status
 pub async fn health(
     Query(query): Query<HealthQuery>,
@@ -78,20 +162,36 @@ pub async fn health(
     }
 }
 
-/// List all services
+/// List all services, or those with a tag. `href` is the URL for the client's network.
 pub async fn list_services(
+    Query(query): Query<ServicesQuery>,
     Extension(state): Extension<Arc<AppState>>,
+    Extension(identity): Extension<Identity>,
+    Extension(ctx): Extension<RenderContext>,
 ) -> Result<impl IntoResponse, VestaError> {
-    let config = state.get_config_manager().read_config()?;
+    let config = state
+        .get_config_manager()
+        .read_config()?
+        .visible_to(&identity);
 
     let mut services = Vec::new();
-    for (group_id, group) in &config.groups {
-        for service in &group.services {
+    for (group_id, group) in config.all_groups() {
+        let tagged = group.services.iter().filter(|service| {
+            query
+                .tag
+                .as_ref()
+                .is_none_or(|tag| service.tags.contains(tag))
+        });
+        for service in tagged {
             services.push(json!({
                 "group": group_id,
                 "group_name": group.name,
                 "title": service.title,
-                "href": service.href,
+                "href": ctx.service_href(service),
+                "hrefs": service.hrefs,
+                "description": service.description,
+                "tags": service.tags,
+                "aliases": service.aliases,
                 "has_widget": service.widget.is_some(),
                 "has_ping": service.ping.is_some(),
                 "widget_type": service.widget.as_ref().map(|w| &w.name)
@@ -105,6 +205,64 @@ pub async fn list_services(
     })))
 }
 
+/// Search services, QuickLinks and the web, for the command palette
+pub async fn search(
+    Query(query): Query<SearchQuery>,
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(identity): Extension<Identity>,
+    Extension(ctx): Extension<RenderContext>,
+) -> Result<impl IntoResponse, VestaError> {
+    let config = state
+        .get_config_manager()
+        .read_config()?
+        .visible_to(&identity);
+    let results = search::search(&ctx, &config, &query.q);
+
+    Ok(jsend::success(json!({
+        "results": results,
+        "count": results.len()
+    })))
+}
+
+/// List API tokens with their last-used timestamps
+pub async fn list_tokens(
+    Extension(state): Extension<Arc<AppState>>,
+) -> Result<impl IntoResponse, VestaError> {
+    let tokens: Vec<_> = state
+        .get_token_store()
+        .list()?
+        .into_iter()
+        .map(|token| {
+            json!({
+                "id": token.id,
+                "name": token.name,
+                "scope": token.scope,
+                "created_at": token.created_at,
+                "last_used_at": token.last_used_at
+            })
+        })
+        .collect();
+
+    Ok(jsend::success(json!({
+        "tokens": tokens,
+        "count": tokens.len()
+    })))
+}
+
+/// Revoke an API token
+pub async fn revoke_token(
+    Path(id): Path<String>,
+    Extension(state): Extension<Arc<AppState>>,
+) -> Result<impl IntoResponse, VestaError> {
+    match state.get_token_store().revoke(&id)? {
+        0 => Err(VestaError::ApiError {
+            status: StatusCode::NOT_FOUND,
+            message: format!("Token '{}' not found", id),
+        }),
+        _ => Ok(jsend::success_message("Token revoked")),
+    }
+}
+
 /// Validate configuration
 pub async fn validate_config(
     Extension(state): Extension<Arc<AppState>>,
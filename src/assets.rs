@@ -0,0 +1,219 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::{Component, Path as FsPath};
+use std::sync::{Arc, LazyLock};
+
+use axum::{
+    Extension,
+    body::{Body, Bytes},
+    extract::Path,
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use rust_embed::RustEmbed;
+use sha2::{Digest, Sha256};
+
+use crate::AppState;
+use crate::config::custom_asset_path;
+use crate::render::{CUSTOM_CSS, CUSTOM_JS};
+
+/// Length of the content hash put into asset file names, in hex digits
+const HASH_LEN: usize = 16;
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+/// Unversioned URLs, e.g. logos referenced from the config, are revalidated with the ETag
+const REVALIDATE: &str = "no-cache";
+
+/// The `static/` directory, compiled into release builds. Debug builds read it
+/// from disk so changes show up without recompiling.
+#[derive(RustEmbed)]
+#[folder = "static/"]
+struct Embedded;
+
+/// Content hashes of the embedded assets, keyed by path
+static HASHES: LazyLock<HashMap<String, String>> = LazyLock::new(|| {
+    Embedded::iter()
+        .filter_map(|path| {
+            let file = Embedded::get(&path)?;
+            Some((path.to_string(), short_hash(&file.metadata.sha256_hash())))
+        })
+        .collect()
+});
+
+/// Contents of a built-in asset
+pub fn embedded(path: &str) -> Option<Cow<'static, [u8]>> {
+    Embedded::get(path).map(|file| file.data)
+}
+
+/// Path of an asset with its content hash, e.g. `app.js` becomes `app.0123456789abcdef.js`
+pub fn versioned_path(path: &str) -> String {
+    match HASHES.get(path) {
+        Some(hash) => insert_hash(path, hash),
+        None => path.to_string(),
+    }
+}
+
+fn insert_hash(path: &str, hash: &str) -> String {
+    let (dir, file) = split_file_name(path);
+    match file.rsplit_once('.') {
+        Some((stem, extension)) => format!("{}{}.{}.{}", dir, stem, hash, extension),
+        None => format!("{}{}.{}", dir, file, hash),
+    }
+}
+
+/// Split a requested path into the asset path and the hash in its file name, if any
+fn strip_hash(path: &str) -> (String, Option<&str>) {
+    let (dir, file) = split_file_name(path);
+    let parts: Vec<&str> = file.split('.').collect();
+    let hash_index = match parts.len() {
+        0 | 1 => None,
+        // `name.hash`, for files without an extension
+        2 => Some(1),
+        len => Some(len - 2),
+    };
+
+    match hash_index {
+        Some(index) if is_hash(parts[index]) => {
+            let mut parts = parts;
+            let hash = parts.remove(index);
+            (format!("{}{}", dir, parts.join(".")), Some(hash))
+        }
+        _ => (path.to_string(), None),
+    }
+}
+
+fn split_file_name(path: &str) -> (&str, &str) {
+    match path.rfind('/') {
+        Some(index) => path.split_at(index + 1),
+        None => ("", path),
+    }
+}
+
+fn is_hash(part: &str) -> bool {
+    part.len() == HASH_LEN && part.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Hex digits of the start of a digest, [`HASH_LEN`] of them
+pub fn short_hash(digest: &[u8]) -> String {
+    digest[..HASH_LEN / 2]
+        .iter()
+        .fold(String::with_capacity(HASH_LEN), |mut hex, byte| {
+            let _ = write!(hex, "{:02x}", byte);
+            hex
+        })
+}
+
+/// Serve `/static/{*path}` from the embedded assets, preferring files in
+/// `server.assets_dir` when it is set
+pub async fn serve_asset(
+    Extension(state): Extension<Arc<AppState>>,
+    Path(path): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let (path, requested_hash) = strip_hash(&path);
+
+    let assets_dir = state
+        .get_config_manager()
+        .read_config()
+        .ok()
+        .and_then(|config| config.server.assets_dir.clone());
+    let from_dir = match assets_dir {
+        Some(dir) => read_from_dir(&dir, &path).await,
+        None => None,
+    };
+    // Files missing from the override directory fall back to the built-in ones
+    let asset = from_dir.or_else(|| {
+        Embedded::get(&path).map(|file| (file.data, short_hash(&file.metadata.sha256_hash())))
+    });
+    let Some((data, hash)) = asset else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let cache_control = if requested_hash == Some(hash.as_str()) {
+        IMMUTABLE
+    } else {
+        REVALIDATE
+    };
+    asset_response(&headers, &path, data, &hash, cache_control)
+}
+
+/// Serve `/custom.css` from next to the config file
+pub async fn serve_custom_css(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Response {
+    serve_custom_asset(&state, CUSTOM_CSS, &headers).await
+}
+
+/// Serve `/custom.js` from next to the config file
+pub async fn serve_custom_js(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Response {
+    serve_custom_asset(&state, CUSTOM_JS, &headers).await
+}
+
+async fn serve_custom_asset(state: &AppState, name: &str, headers: &HeaderMap) -> Response {
+    let path = custom_asset_path(state.get_config_manager().config_path(), name);
+    let Ok(data) = tokio::fs::read(path).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let hash = short_hash(&Sha256::digest(&data));
+    asset_response(headers, name, Cow::Owned(data), &hash, REVALIDATE)
+}
+
+fn asset_response(
+    headers: &HeaderMap,
+    path: &str,
+    data: Cow<'static, [u8]>,
+    hash: &str,
+    cache_control: &'static str,
+) -> Response {
+    let etag = format!("\"{}\"", hash);
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.split(',').any(|tag| tag.trim() == etag));
+
+    let mut response = if not_modified {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        let content_type = mime_guess::from_path(path).first_or_octet_stream();
+        // Assets built into the binary are served without copying them
+        let body = match data {
+            Cow::Borrowed(data) => Body::from(Bytes::from_static(data)),
+            Cow::Owned(data) => Body::from(data),
+        };
+        (
+            [(header::CONTENT_TYPE, content_type.essence_str().to_string())],
+            body,
+        )
+            .into_response()
+    };
+
+    let response_headers = response.headers_mut();
+    response_headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static(cache_control),
+    );
+    if let Ok(etag) = HeaderValue::from_str(&etag) {
+        response_headers.insert(header::ETAG, etag);
+    }
+    response
+}
+
+/// Read an asset from the override directory, refusing paths that escape it
+async fn read_from_dir(dir: &FsPath, path: &str) -> Option<(Cow<'static, [u8]>, String)> {
+    let relative = FsPath::new(path);
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+
+    let data = tokio::fs::read(dir.join(relative)).await.ok()?;
+    let hash = short_hash(&Sha256::digest(&data));
+    Some((Cow::Owned(data), hash))
+}
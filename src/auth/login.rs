@@ -0,0 +1,320 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Form,
+    extract::Query,
+    http::{HeaderMap, StatusCode, header},
+    response::{AppendHeaders, IntoResponse, Redirect, Response},
+};
+use serde::Deserialize;
+
+use crate::{
+    AppState,
+    auth::{
+        oidc::PENDING_LOGIN_TTL,
+        password,
+        session::{self, Session, SessionSource},
+        session_ttl,
+    },
+    config::AuthMethod,
+    error::VestaError,
+    render::RenderContext,
+    templates::login_page,
+};
+
+#[derive(Deserialize)]
+pub struct LoginQuery {
+    pub next: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct LoginForm {
+    pub username: String,
+    pub password: String,
+    pub next: Option<String>,
+}
+
+/// Only follow redirects that stay on this site. `next` is relative to the base path.
+/// Browsers read `\` as `/` and drop tabs and newlines, so `/\evil.com` would
+/// leave the site just like `//evil.com`.
+fn safe_next(next: Option<&str>) -> &str {
+    match next {
+        Some(next)
+            if next.starts_with('/')
+                && !next.starts_with("//")
+                && !next.contains(|c: char| c == '\\' || c.is_control()) =>
+        {
+            next
+        }
+        _ => "/",
+    }
+}
+
+#[derive(Deserialize)]
+pub struct OidcCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+/// Which login options the login page should offer
+pub struct LoginOptions {
+    pub password: bool,
+    pub oidc_provider: Option<String>,
+}
+
+fn login_options(state: &AppState) -> LoginOptions {
+    match state.get_config_manager().read_config() {
+        Ok(config) => LoginOptions {
+            password: config.auth.has_method(AuthMethod::Session),
+            oidc_provider: config.auth.oidc().map(|oidc| oidc.provider_name.clone()),
+        },
+        Err(_) => LoginOptions {
+            password: true,
+            oidc_provider: None,
+        },
+    }
+}
+
+pub async fn login_form(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(ctx): Extension<RenderContext>,
+    Query(query): Query<LoginQuery>,
+) -> impl IntoResponse {
+    login_page(
+        &ctx,
+        &login_options(&state),
+        safe_next(query.next.as_deref()),
+        None,
+    )
+}
+
+/// Redirect the browser to the identity provider
+pub async fn oidc_login(
+    Extension(state): Extension<Arc<AppState>>,
+    Query(query): Query<LoginQuery>,
+) -> Result<Response, VestaError> {
+    let (oidc_config, secure) = {
+        let config = state.get_config_manager().read_config()?;
+        (config.auth.oidc().cloned(), config.server.tls.is_some())
+    };
+    let oidc_config = oidc_config
+        .ok_or_else(|| VestaError::Forbidden("OpenID Connect login is not enabled".to_string()))?;
+
+    let (url, login_state) = state
+        .get_oidc_client()
+        .authorization_url(&oidc_config, safe_next(query.next.as_deref()))
+        .await?;
+    Ok((
+        [(
+            header::SET_COOKIE,
+            session::oidc_state_cookie(&login_state, PENDING_LOGIN_TTL, secure),
+        )],
+        Redirect::to(&url),
+    )
+        .into_response())
+}
+
+/// Handle the identity provider redirecting back after login
+pub async fn oidc_callback(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(ctx): Extension<RenderContext>,
+    Query(query): Query<OidcCallbackQuery>,
+    headers: HeaderMap,
+) -> Result<Response, VestaError> {
+    let (oidc_config, ttl, secure) = {
+        let config = state.get_config_manager().read_config()?;
+        (
+            config.auth.oidc().cloned(),
+            session_ttl(&config.auth),
+            config.server.tls.is_some(),
+        )
+    };
+    let oidc_config = oidc_config
+        .ok_or_else(|| VestaError::Forbidden("OpenID Connect login is not enabled".to_string()))?;
+
+    // A callback started in another browser, e.g. a link from an attacker that
+    // would sign the victim in as the attacker
+    let browser_state = session::get_cookie(&headers, session::OIDC_STATE_COOKIE);
+    let result = match (&query.code, &query.state, &query.error) {
+        (Some(_), Some(login_state), None) if browser_state != Some(login_state.as_str()) => Err(
+            VestaError::Internal("Login state does not match this browser".to_string()),
+        ),
+        (Some(code), Some(login_state), None) => {
+            state
+                .get_oidc_client()
+                .complete_login(&oidc_config, code, login_state)
+                .await
+        }
+        _ => Err(VestaError::Internal(
+            query
+                .error_description
+                .clone()
+                .or_else(|| query.error.clone())
+                .unwrap_or_else(|| "Missing authorization code".to_string()),
+        )),
+    };
+
+    let login = match result {
+        Ok(login) => login,
+        Err(e) => {
+            tracing::warn!(error = %e, "OpenID Connect login failed");
+            return Ok((
+                StatusCode::UNAUTHORIZED,
+                [(header::SET_COOKIE, session::clear_oidc_state_cookie())],
+                login_page(
+                    &ctx,
+                    &login_options(&state),
+                    "/",
+                    Some(ctx.locale.t("login.provider_failed")),
+                ),
+            )
+                .into_response());
+        }
+    };
+
+    let token = state
+        .get_session_store()
+        .create(
+            &login.username,
+            login.roles,
+            SessionSource::Oidc(login.tokens),
+            ttl,
+        )
+        .await;
+
+    // Two cookies, which an array of headers would overwrite with each other
+    Ok((
+        AppendHeaders([
+            (
+                header::SET_COOKIE,
+                session::session_cookie(&token, ttl, secure),
+            ),
+            (header::SET_COOKIE, session::clear_oidc_state_cookie()),
+        ]),
+        Redirect::to(&ctx.url(&login.next)),
+    )
+        .into_response())
+}
+
+pub async fn login(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(ctx): Extension<RenderContext>,
+    Form(form): Form<LoginForm>,
+) -> Result<Response, VestaError> {
+    let next = safe_next(form.next.as_deref());
+
+    let (user, ttl, secure) = {
+        let config = state.get_config_manager().read_config()?;
+        let auth = &config.auth;
+        if !auth.enabled || !auth.has_method(AuthMethod::Session) {
+            return Ok(Redirect::to(&ctx.url(next)).into_response());
+        }
+
+        let user = auth.get_user(&form.username).cloned();
+        (user, session_ttl(auth), config.server.tls.is_some())
+    };
+
+    let password_hash = user.as_ref().map(|user| user.password_hash.clone());
+    let verified = password::verify_password_blocking(form.password, password_hash).await;
+    let user = user.filter(|_| verified);
+
+    let Some(user) = user else {
+        tracing::warn!(username = %form.username, "Failed login attempt");
+        return Ok((
+            StatusCode::UNAUTHORIZED,
+            login_page(
+                &ctx,
+                &login_options(&state),
+                next,
+                Some(ctx.locale.t("login.invalid_credentials")),
+            ),
+        )
+            .into_response());
+    };
+
+    let token = state
+        .get_session_store()
+        .create(&user.username, user.roles, SessionSource::Local, ttl)
+        .await;
+
+    Ok((
+        [(
+            header::SET_COOKIE,
+            session::session_cookie(&token, ttl, secure),
+        )],
+        Redirect::to(&ctx.url(next)),
+    )
+        .into_response())
+}
+
+pub async fn logout(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(ctx): Extension<RenderContext>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let mut redirect = ctx.url("/login");
+
+    if let Some(token) = session::get_cookie(&headers, session::SESSION_COOKIE) {
+        let session = state.get_session_store().get(token).await;
+        state.get_session_store().remove(token).await;
+
+        // End the session at the identity provider too, if it supports it
+        if let Some(Session {
+            source: SessionSource::Oidc(tokens),
+            ..
+        }) = session
+        {
+            let oidc_config = state
+                .get_config_manager()
+                .read_config()
+                .ok()
+                .and_then(|config| config.auth.oidc().cloned());
+            if let Some(oidc_config) = oidc_config
+                && let Some(url) = state
+                    .get_oidc_client()
+                    .logout_url(&oidc_config, &tokens.id_token)
+                    .await
+            {
+                redirect = url;
+            }
+        }
+    }
+
+    (
+        [(header::SET_COOKIE, session::clear_session_cookie())],
+        Redirect::to(&redirect),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::safe_next;
+
+    #[test]
+    fn safe_next_keeps_paths_on_this_site() {
+        assert_eq!(safe_next(Some("/")), "/");
+        assert_eq!(
+            safe_next(Some("/settings?tab=tokens")),
+            "/settings?tab=tokens"
+        );
+        assert_eq!(safe_next(Some("/a//b")), "/a//b");
+    }
+
+    #[test]
+    fn safe_next_rejects_other_sites() {
+        for next in [
+            "//evil.com",
+            "/\\evil.com",
+            "/\t/evil.com",
+            "https://evil.com",
+            "evil.com",
+            "javascript:alert(1)",
+            "",
+        ] {
+            assert_eq!(safe_next(Some(next)), "/", "{next:?}");
+        }
+        assert_eq!(safe_next(None), "/");
+    }
+}
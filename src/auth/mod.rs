@@ -0,0 +1,606 @@
+pub mod login;
+pub mod oidc;
+pub mod password;
+pub mod session;
+pub mod tokens;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{
+    Extension,
+    extract::{ConnectInfo, Request},
+    http::{HeaderMap, HeaderValue, Method, header},
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde::Serialize;
+
+use crate::{
+    AppState,
+    auth::{
+        session::{Session, SessionSource},
+        tokens::{ApiToken, TokenScope},
+    },
+    config::{AuthConfig, AuthMethod, Dashboard},
+    error::VestaError,
+    render::{RenderContext, encode_query_value},
+    server::PeerAddr,
+};
+
+/// Level of access granted to an identity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Access {
+    None,
+    Viewer,
+    Admin,
+}
+
+/// The authenticated user of a request
+#[derive(Debug, Clone, Serialize)]
+pub struct Identity {
+    pub username: String,
+    pub roles: Vec<String>,
+    pub access: Access,
+    /// Set when the request was authenticated with an API token
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_scope: Option<TokenScope>,
+}
+
+impl Identity {
+    pub fn new(auth: &AuthConfig, username: &str, roles: Vec<String>) -> Self {
+        let has_any = |wanted: &[String]| roles.iter().any(|role| wanted.contains(role));
+
+        let access = if has_any(&auth.admin_roles) {
+            Access::Admin
+        } else if auth.viewer_roles.is_empty() || has_any(&auth.viewer_roles) {
+            Access::Viewer
+        } else {
+            Access::None
+        };
+
+        Self {
+            username: username.to_string(),
+            roles,
+            access,
+            token_scope: None,
+        }
+    }
+
+    pub fn from_token(token: &ApiToken) -> Self {
+        let access = match token.scope {
+            TokenScope::Admin => Access::Admin,
+            TokenScope::Read | TokenScope::Reload => Access::Viewer,
+        };
+
+        Self {
+            username: format!("token:{}", token.name),
+            roles: Vec::new(),
+            access,
+            token_scope: Some(token.scope),
+        }
+    }
+
+    /// Identity used for every request when authentication is disabled
+    pub fn anonymous() -> Self {
+        Self {
+            username: "anonymous".to_string(),
+            roles: Vec::new(),
+            access: Access::Admin,
+            token_scope: None,
+        }
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.access == Access::Admin
+    }
+
+    pub fn can_view(&self) -> bool {
+        self.access >= Access::Viewer
+    }
+
+    pub fn can_reload(&self) -> bool {
+        self.is_admin() || self.token_scope == Some(TokenScope::Reload)
+    }
+}
+
+/// Resolve the identity of a request and store it in the request extensions.
+///
+/// This never rejects a request; access is enforced by [`require_viewer`] and
+/// [`require_admin`] on the routes that need it.
+pub async fn authenticate(
+    Extension(state): Extension<Arc<AppState>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let identity = match state.get_config_manager().read_config() {
+        Ok(config) => {
+            let peer = request
+                .extensions()
+                .get::<ConnectInfo<PeerAddr>>()
+                .map(|info| info.0);
+            resolve_identity(&config, request.headers(), peer)
+        }
+        Err(e) => {
+            tracing::error!(error = %e, "Error reading config for authentication");
+            None
+        }
+    };
+
+    let identity = match identity {
+        Some(Resolved::Ready(identity)) => Some(identity),
+        Some(Resolved::Session(token)) => resolve_session(&state, &token).await,
+        Some(Resolved::Basic(username, password)) => {
+            resolve_basic(&state, &username, &password).await
+        }
+        Some(Resolved::Bearer(token)) => state
+            .get_token_store()
+            .authenticate(&token)
+            .map(|token| Identity::from_token(&token)),
+        None => None,
+    };
+
+    if let Some(identity) = identity {
+        request.extensions_mut().insert(identity);
+    }
+
+    next.run(request).await
+}
+
+/// Reject requests without an identity that may view the dashboard
+pub async fn require_viewer(
+    Extension(state): Extension<Arc<AppState>>,
+    identity: Option<Extension<Identity>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    require(
+        &state,
+        identity,
+        request,
+        next,
+        Identity::can_view,
+        "view this dashboard",
+    )
+    .await
+}
+
+/// Reject requests without an identity that may reload the configuration
+pub async fn require_reload(
+    Extension(state): Extension<Arc<AppState>>,
+    identity: Option<Extension<Identity>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    require(
+        &state,
+        identity,
+        request,
+        next,
+        Identity::can_reload,
+        "reload the configuration",
+    )
+    .await
+}
+
+/// Reject requests without an administrator identity
+pub async fn require_admin(
+    Extension(state): Extension<Arc<AppState>>,
+    identity: Option<Extension<Identity>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    require(
+        &state,
+        identity,
+        request,
+        next,
+        Identity::is_admin,
+        "administer Vesta",
+    )
+    .await
+}
+
+async fn require(
+    state: &AppState,
+    identity: Option<Extension<Identity>>,
+    request: Request,
+    next: Next,
+    allowed: fn(&Identity) -> bool,
+    action: &str,
+) -> Response {
+    match identity {
+        Some(Extension(identity)) if allowed(&identity) => next.run(request).await,
+        Some(Extension(identity)) => VestaError::Forbidden(format!(
+            "'{}' is not allowed to {}",
+            identity.username, action
+        ))
+        .into_response(),
+        None => unauthorized(state, &request),
+    }
+}
+
+/// Reject requests that change state unless they come from a page of this site.
+///
+/// Browsers resend cookies, Basic credentials and proxy logins with requests that
+/// other sites make, so a state-changing request must carry an `Origin` matching
+/// the host, `Sec-Fetch-Site: same-origin` or an `HX-Request` header, which other
+/// sites can't add without a CORS preflight. API tokens are never sent by the
+/// browser on its own, so requests authenticated with one are let through.
+pub async fn require_same_origin(
+    Extension(state): Extension<Arc<AppState>>,
+    identity: Option<Extension<Identity>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let safe_method = matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    );
+    let token = identity.is_some_and(|Extension(identity)| identity.token_scope.is_some());
+    if safe_method || token {
+        return next.run(request).await;
+    }
+
+    let trusted = match state.get_config_manager().read_config() {
+        Ok(config) => request
+            .extensions()
+            .get::<ConnectInfo<PeerAddr>>()
+            .is_some_and(|info| info.0.is_trusted_proxy(&config.server)),
+        Err(_) => false,
+    };
+    if is_same_origin(request.headers(), trusted) {
+        next.run(request).await
+    } else {
+        VestaError::Forbidden("Cross-site request rejected".to_string()).into_response()
+    }
+}
+
+/// `forwarded_host` is set when `X-Forwarded-Host` comes from a trusted proxy
+fn is_same_origin(headers: &HeaderMap, forwarded_host: bool) -> bool {
+    if headers.contains_key("HX-Request") {
+        return true;
+    }
+
+    let header_value = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    match header_value("Sec-Fetch-Site") {
+        Some("same-origin") => return true,
+        // `same-site` and `cross-site` come from other pages, `none` from the
+        // address bar, which can't send a POST
+        Some(_) => return false,
+        None => {}
+    }
+
+    let Some(origin) = header_value(header::ORIGIN.as_str()) else {
+        return false;
+    };
+    let Some(origin_host) = origin
+        .strip_prefix("https://")
+        .or_else(|| origin.strip_prefix("http://"))
+    else {
+        return false;
+    };
+    let host = header_value("X-Forwarded-Host")
+        .filter(|_| forwarded_host)
+        .or_else(|| header_value(header::HOST.as_str()));
+    host.is_some_and(|host| host.eq_ignore_ascii_case(origin_host))
+}
+
+enum Resolved {
+    Ready(Identity),
+    Session(String),
+    Basic(String, String),
+    Bearer(String),
+}
+
+/// Work out how a request is authenticated without holding the config lock
+/// across an await point
+fn resolve_identity(
+    config: &Dashboard,
+    headers: &HeaderMap,
+    peer: Option<PeerAddr>,
+) -> Option<Resolved> {
+    let auth = &config.auth;
+    if !auth.enabled {
+        return Some(Resolved::Ready(Identity::anonymous()));
+    }
+
+    // API tokens work regardless of the configured login methods
+    if let Some(token) = bearer_token(headers) {
+        return Some(Resolved::Bearer(token.to_string()));
+    }
+
+    for method in &auth.methods {
+        let resolved = match method {
+            AuthMethod::Proxy => resolve_proxy(config, headers, peer).map(Resolved::Ready),
+            AuthMethod::Basic => basic_credentials(headers)
+                .map(|(username, password)| Resolved::Basic(username, password)),
+            AuthMethod::Session | AuthMethod::Oidc => {
+                session::get_cookie(headers, session::SESSION_COOKIE)
+                    .map(|token| Resolved::Session(token.to_string()))
+            }
+        };
+        if resolved.is_some() {
+            return resolved;
+        }
+    }
+
+    None
+}
+
+fn resolve_proxy(
+    config: &Dashboard,
+    headers: &HeaderMap,
+    peer: Option<PeerAddr>,
+) -> Option<Identity> {
+    if !peer?.is_trusted_proxy(&config.server) {
+        return None;
+    }
+
+    let proxy = &config.auth.proxy;
+    let username = headers
+        .get(proxy.user_header.as_str())?
+        .to_str()
+        .ok()?
+        .trim();
+    if username.is_empty() {
+        return None;
+    }
+
+    let mut roles: Vec<String> = headers
+        .get(proxy.groups_header.as_str())
+        .and_then(|value| value.to_str().ok())
+        .map(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|role| !role.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    if roles.is_empty() {
+        roles = proxy.default_roles.clone();
+    }
+
+    Some(Identity::new(&config.auth, username, roles))
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    value.strip_prefix("Bearer ").map(str::trim)
+}
+
+fn basic_credentials(headers: &HeaderMap) -> Option<(String, String)> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let encoded = value.strip_prefix("Basic ")?;
+    let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    let (username, password) = decoded.split_once(':')?;
+    Some((username.to_string(), password.to_string()))
+}
+
+async fn resolve_basic(state: &AppState, username: &str, password: &str) -> Option<Identity> {
+    let user = {
+        let config = state.get_config_manager().read_config().ok()?;
+        config.auth.get_user(username).cloned()
+    };
+    let password_hash = user.as_ref().map(|user| user.password_hash.as_str());
+    if !state
+        .get_credential_cache()
+        .verify(username, password, password_hash)
+        .await
+    {
+        return None;
+    }
+
+    let user = user?;
+    let config = state.get_config_manager().read_config().ok()?;
+    Some(Identity::new(&config.auth, &user.username, user.roles))
+}
+
+async fn resolve_session(state: &AppState, token: &str) -> Option<Identity> {
+    let store = state.get_session_store();
+    let mut session = store.get(token).await?;
+
+    if needs_refresh(&session) {
+        // A page load sends many requests at once; one of them refreshes the
+        // session while the others wait for it, since providers that rotate
+        // refresh tokens only accept each one once
+        let lock = store.refresh_lock(token).await;
+        let _guard = lock.lock().await;
+        session = store.get(token).await?;
+        if needs_refresh(&session) {
+            session = refresh_session(state, token, session).await?;
+        }
+    }
+
+    let config = state.get_config_manager().read_config().ok()?;
+    let auth = &config.auth;
+
+    match session.source {
+        SessionSource::Local if auth.has_method(AuthMethod::Session) => {
+            // Local users removed from the config lose their sessions immediately
+            let user = auth.get_user(&session.username)?;
+            Some(Identity::new(auth, &user.username, user.roles.clone()))
+        }
+        SessionSource::Oidc(_) if auth.oidc().is_some() => {
+            Some(Identity::new(auth, &session.username, session.roles))
+        }
+        _ => None,
+    }
+}
+
+fn needs_refresh(session: &Session) -> bool {
+    matches!(&session.source, SessionSource::Oidc(tokens) if tokens.expires_at <= Instant::now())
+}
+
+/// Refresh the OIDC tokens of a session, removing it when the provider refuses
+async fn refresh_session(state: &AppState, token: &str, mut session: Session) -> Option<Session> {
+    let SessionSource::Oidc(tokens) = &session.source else {
+        return Some(session);
+    };
+    let oidc_config = {
+        let config = state.get_config_manager().read_config().ok()?;
+        config.auth.oidc().cloned()
+    };
+    let Some(oidc_config) = oidc_config else {
+        state.get_session_store().remove(token).await;
+        return None;
+    };
+
+    // Sessions without a refresh token keep their roles until they expire
+    if tokens.refresh_token.is_none() {
+        return Some(session);
+    }
+    match state.get_oidc_client().refresh(&oidc_config, tokens).await {
+        Ok((roles, tokens)) => {
+            if let Some(roles) = roles {
+                session.roles = roles;
+            }
+            session.source = SessionSource::Oidc(tokens);
+            state
+                .get_session_store()
+                .update(token, session.clone())
+                .await;
+            Some(session)
+        }
+        Err(e) => {
+            tracing::warn!(
+                username = %session.username,
+                error = %e,
+                "Failed to refresh OIDC session"
+            );
+            state.get_session_store().remove(token).await;
+            None
+        }
+    }
+}
+
+fn unauthorized(state: &AppState, request: &Request) -> Response {
+    if wants_html(request) {
+        let ctx = request
+            .extensions()
+            .get::<RenderContext>()
+            .cloned()
+            .unwrap_or_default();
+        // Below the base path, so it stays valid if the base path changes
+        let next = request
+            .uri()
+            .path_and_query()
+            .map(|pq| pq.as_str())
+            .unwrap_or("/");
+        return Redirect::to(&ctx.url(&format!("/login?next={}", encode_query_value(next))))
+            .into_response();
+    }
+
+    let mut response = VestaError::Unauthorized.into_response();
+    let basic_enabled = state
+        .get_config_manager()
+        .read_config()
+        .map(|config| config.auth.has_method(AuthMethod::Basic))
+        .unwrap_or(false);
+    if basic_enabled {
+        response.headers_mut().insert(
+            header::WWW_AUTHENTICATE,
+            HeaderValue::from_static("Basic realm=\"Vesta\", charset=\"UTF-8\""),
+        );
+    }
+    response
+}
+
+/// Page navigations get redirected to the login form, everything else gets a 401
+fn wants_html(request: &Request) -> bool {
+    request.method() == Method::GET
+        && !request.uri().path().starts_with("/api/")
+        && !request.headers().contains_key("HX-Request")
+        && request
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|accept| accept.contains("text/html"))
+}
+
+pub fn session_ttl(auth: &AuthConfig) -> Duration {
+    Duration::from_secs(auth.session_ttl)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn same_origin_accepts_htmx_fetch_metadata_and_matching_origin() {
+        assert!(is_same_origin(&headers(&[("hx-request", "true")]), false));
+        assert!(is_same_origin(
+            &headers(&[("sec-fetch-site", "same-origin")]),
+            false
+        ));
+        assert!(is_same_origin(
+            &headers(&[
+                ("origin", "https://dash.example.com"),
+                ("host", "dash.example.com")
+            ]),
+            false
+        ));
+        assert!(is_same_origin(
+            &headers(&[
+                ("origin", "http://127.0.0.1:3000"),
+                ("host", "127.0.0.1:3000")
+            ]),
+            false
+        ));
+    }
+
+    #[test]
+    fn same_origin_rejects_requests_without_any_marker() {
+        assert!(!is_same_origin(
+            &headers(&[("host", "dash.example.com")]),
+            false
+        ));
+    }
+
+    #[test]
+    fn same_origin_rejects_other_origins() {
+        for origin in [
+            "https://evil.com",
+            "null",
+            "https://dash.example.com.evil.com",
+        ] {
+            let headers = headers(&[("origin", origin), ("host", "dash.example.com")]);
+            assert!(!is_same_origin(&headers, false), "{origin}");
+        }
+    }
+
+    #[test]
+    fn same_origin_rejects_cross_site_fetch_metadata() {
+        for site in ["cross-site", "same-site", "none"] {
+            let headers = headers(&[
+                ("sec-fetch-site", site),
+                ("origin", "https://dash.example.com"),
+                ("host", "dash.example.com"),
+            ]);
+            assert!(!is_same_origin(&headers, false), "{site}");
+        }
+    }
+
+    #[test]
+    fn same_origin_only_believes_forwarded_host_from_trusted_proxies() {
+        let headers = headers(&[
+            ("origin", "https://evil.com"),
+            ("host", "127.0.0.1:3000"),
+            ("x-forwarded-host", "evil.com"),
+        ]);
+        assert!(!is_same_origin(&headers, false));
+        assert!(is_same_origin(&headers, true));
+    }
+}
@@ -0,0 +1,178 @@
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+use argon2::{
+    Argon2,
+    password_hash::{
+        PasswordHash, PasswordHasher, PasswordVerifier, SaltString,
+        rand_core::{OsRng, RngCore},
+    },
+};
+use moka::future::Cache;
+use sha2::{Digest, Sha256};
+
+use crate::error::{VestaError, VestaResult};
+
+/// Hash verified when a username is unknown, so that the response time does not
+/// reveal which usernames exist
+static DUMMY_HASH: LazyLock<String> =
+    LazyLock::new(|| hash_password("vesta-dummy-password").unwrap_or_default());
+
+const MAX_CACHED_CREDENTIALS: u64 = 1_000;
+/// How long Basic auth credentials are trusted after a full verification
+const CREDENTIAL_CACHE_TTL: Duration = Duration::from_secs(60);
+
+pub fn hash_password(password: &str) -> VestaResult<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| VestaError::Internal(format!("Failed to hash password: {}", e)))
+}
+
+fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .map(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+/// Burn the same amount of time as a real verification
+fn verify_dummy(password: &str) {
+    let _ = verify_password(password, &DUMMY_HASH);
+}
+
+/// Verify `password` on a blocking thread, as Argon2 takes tens of milliseconds
+/// and about 19 MiB. Without a hash, i.e. for an unknown user, a dummy hash is
+/// verified instead and the result is always `false`.
+pub async fn verify_password_blocking(password: String, password_hash: Option<String>) -> bool {
+    tokio::task::spawn_blocking(move || match password_hash {
+        Some(password_hash) => verify_password(&password, &password_hash),
+        None => {
+            verify_dummy(&password);
+            false
+        }
+    })
+    .await
+    .unwrap_or(false)
+}
+
+/// Basic auth credentials that verified recently. Browsers send them with every
+/// request, including widget polls and pings, which would otherwise each cost a
+/// full Argon2 verification.
+pub struct CredentialCache {
+    verified: Cache<String, ()>,
+    /// Keys the cache, so it holds nothing a password can be checked against
+    /// outside this process
+    secret: [u8; 32],
+}
+
+impl CredentialCache {
+    pub fn new() -> Arc<Self> {
+        let mut secret = [0u8; 32];
+        OsRng.fill_bytes(&mut secret);
+        Arc::new(Self {
+            verified: Cache::builder()
+                .max_capacity(MAX_CACHED_CREDENTIALS)
+                .time_to_live(CREDENTIAL_CACHE_TTL)
+                .build(),
+            secret,
+        })
+    }
+
+    /// Like [`verify_password_blocking`], but remembers successful verifications.
+    /// The hash is part of the key, so changing a password in the config takes
+    /// effect at once; failures are never cached.
+    pub async fn verify(
+        &self,
+        username: &str,
+        password: &str,
+        password_hash: Option<&str>,
+    ) -> bool {
+        let Some(password_hash) = password_hash else {
+            return verify_password_blocking(password.to_string(), None).await;
+        };
+
+        let key = self.key(username, password, password_hash);
+        if self.verified.contains_key(&key) {
+            return true;
+        }
+        let verified =
+            verify_password_blocking(password.to_string(), Some(password_hash.to_string())).await;
+        if verified {
+            self.verified.insert(key, ()).await;
+        }
+        verified
+    }
+
+    fn key(&self, username: &str, password: &str, password_hash: &str) -> String {
+        let mut hasher = Sha256::new();
+        for part in [
+            &self.secret[..],
+            username.as_bytes(),
+            password.as_bytes(),
+            password_hash.as_bytes(),
+        ] {
+            // Length-prefixed, so the parts can't run into each other
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part);
+        }
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashed_passwords_verify() {
+        let hash = hash_password("correct horse").unwrap();
+        assert!(verify_password("correct horse", &hash));
+        assert!(!verify_password("battery staple", &hash));
+        assert!(!verify_password("correct horse", "not a PHC string"));
+    }
+
+    #[tokio::test]
+    async fn unknown_users_never_verify() {
+        assert!(!verify_password_blocking("anything".to_string(), None).await);
+        assert!(
+            !CredentialCache::new()
+                .verify("nobody", "anything", None)
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn credential_cache_only_remembers_successes() {
+        let hash = hash_password("secret").unwrap();
+        let cache = CredentialCache::new();
+
+        assert!(!cache.verify("admin", "wrong", Some(&hash)).await);
+        assert!(
+            !cache
+                .verified
+                .contains_key(&cache.key("admin", "wrong", &hash))
+        );
+        assert!(cache.verify("admin", "secret", Some(&hash)).await);
+        assert!(
+            cache
+                .verified
+                .contains_key(&cache.key("admin", "secret", &hash))
+        );
+    }
+
+    #[tokio::test]
+    async fn credential_cache_forgets_passwords_changed_in_the_config() {
+        let cache = CredentialCache::new();
+        let old_hash = hash_password("secret").unwrap();
+        assert!(cache.verify("admin", "secret", Some(&old_hash)).await);
+
+        let new_hash = hash_password("new secret").unwrap();
+        assert!(!cache.verify("admin", "secret", Some(&new_hash)).await);
+        assert!(cache.verify("admin", "new secret", Some(&new_hash)).await);
+    }
+}
@@ -0,0 +1,224 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use axum::http::{HeaderMap, header};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use moka::future::Cache;
+use tokio::sync::Mutex;
+
+pub const SESSION_COOKIE: &str = "vesta_session";
+/// Ties an OpenID Connect callback to the browser that started the login
+pub const OIDC_STATE_COOKIE: &str = "vesta_oidc_state";
+
+const MAX_SESSIONS: u64 = 10_000;
+const MAX_SESSION_TTL: Duration = Duration::from_secs(60 * 60 * 24 * 30);
+/// How long an unused refresh lock is kept
+const REFRESH_LOCK_TTL: Duration = Duration::from_secs(60 * 5);
+
+/// Tokens kept for sessions that were established through OpenID Connect
+#[derive(Debug, Clone)]
+pub struct OidcTokens {
+    pub id_token: String,
+    pub refresh_token: Option<String>,
+    /// When the tokens should be refreshed to pick up revocations and role changes
+    pub expires_at: Instant,
+}
+
+#[derive(Debug, Clone)]
+pub enum SessionSource {
+    /// Logged in with a `[[auth.users]]` password; roles come from the config
+    Local,
+    /// Logged in through the identity provider; roles come from its claims
+    Oidc(OidcTokens),
+}
+
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub username: String,
+    pub roles: Vec<String>,
+    pub source: SessionSource,
+    pub expires_at: Instant,
+}
+
+impl Session {
+    pub fn is_expired(&self) -> bool {
+        Instant::now() >= self.expires_at
+    }
+}
+
+/// In-memory store of login sessions keyed by their random cookie token
+pub struct SessionStore {
+    sessions: Cache<String, Session>,
+    /// Held while a session's OIDC tokens are refreshed
+    refresh_locks: Cache<String, Arc<Mutex<()>>>,
+}
+
+impl SessionStore {
+    pub fn new() -> Arc<Self> {
+        let sessions = Cache::builder()
+            .max_capacity(MAX_SESSIONS)
+            .time_to_live(MAX_SESSION_TTL)
+            .build();
+        let refresh_locks = Cache::builder()
+            .max_capacity(MAX_SESSIONS)
+            .time_to_idle(REFRESH_LOCK_TTL)
+            .build();
+
+        Arc::new(Self {
+            sessions,
+            refresh_locks,
+        })
+    }
+
+    /// Create a session and return its token
+    pub async fn create(
+        &self,
+        username: &str,
+        roles: Vec<String>,
+        source: SessionSource,
+        ttl: Duration,
+    ) -> String {
+        let token = generate_token();
+        let session = Session {
+            username: username.to_string(),
+            roles,
+            source,
+            expires_at: Instant::now() + ttl.min(MAX_SESSION_TTL),
+        };
+        self.sessions.insert(token.clone(), session).await;
+        token
+    }
+
+    pub async fn update(&self, token: &str, session: Session) {
+        self.sessions.insert(token.to_string(), session).await;
+    }
+
+    pub async fn get(&self, token: &str) -> Option<Session> {
+        let session = self.sessions.get(token).await?;
+        if session.is_expired() {
+            self.sessions.invalidate(token).await;
+            return None;
+        }
+        Some(session)
+    }
+
+    /// The lock to hold while refreshing the session of `token`, so it is
+    /// refreshed once however many requests find it expired
+    pub async fn refresh_lock(&self, token: &str) -> Arc<Mutex<()>> {
+        self.refresh_locks
+            .get_with(token.to_string(), async { Arc::new(Mutex::new(())) })
+            .await
+    }
+
+    pub async fn remove(&self, token: &str) {
+        self.sessions.invalidate(token).await;
+    }
+}
+
+/// Generate a random URL-safe token with 256 bits of entropy
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Extract a cookie value from the request headers
+pub fn get_cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// `secure` should be set when Vesta serves HTTPS itself
+pub fn session_cookie(token: &str, ttl: Duration, secure: bool) -> String {
+    format!(
+        "{}={}; Path=/; HttpOnly; SameSite=Lax; Max-Age={}{}",
+        SESSION_COOKIE,
+        token,
+        ttl.as_secs(),
+        if secure { "; Secure" } else { "" }
+    )
+}
+
+pub fn clear_session_cookie() -> String {
+    format!(
+        "{}=; Path=/; HttpOnly; SameSite=Lax; Max-Age=0",
+        SESSION_COOKIE
+    )
+}
+
+/// Remembers the `state` of a login at the identity provider until its callback
+pub fn oidc_state_cookie(state: &str, ttl: Duration, secure: bool) -> String {
+    format!(
+        "{}={}; Path=/; HttpOnly; SameSite=Lax; Max-Age={}{}",
+        OIDC_STATE_COOKIE,
+        state,
+        ttl.as_secs(),
+        if secure { "; Secure" } else { "" }
+    )
+}
+
+pub fn clear_oidc_state_cookie() -> String {
+    format!(
+        "{}=; Path=/; HttpOnly; SameSite=Lax; Max-Age=0",
+        OIDC_STATE_COOKIE
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn attributes(cookie: &str) -> Vec<&str> {
+        cookie.split("; ").skip(1).collect()
+    }
+
+    #[test]
+    fn session_cookie_is_http_only_and_lax() {
+        let cookie = session_cookie("abc", Duration::from_secs(3600), false);
+        assert!(cookie.starts_with("vesta_session=abc; "));
+        assert_eq!(
+            attributes(&cookie),
+            ["Path=/", "HttpOnly", "SameSite=Lax", "Max-Age=3600"]
+        );
+    }
+
+    #[test]
+    fn session_cookie_is_secure_over_https() {
+        let cookie = session_cookie("abc", Duration::from_secs(3600), true);
+        assert!(attributes(&cookie).contains(&"Secure"));
+    }
+
+    #[test]
+    fn clear_session_cookie_expires_it() {
+        let cookie = clear_session_cookie();
+        assert!(cookie.starts_with("vesta_session=; "));
+        assert!(attributes(&cookie).contains(&"Max-Age=0"));
+    }
+
+    #[test]
+    fn get_cookie_finds_the_named_cookie() {
+        let mut headers = HeaderMap::new();
+        headers.append(header::COOKIE, HeaderValue::from_static("theme=dark"));
+        headers.append(
+            header::COOKIE,
+            HeaderValue::from_static("other_vesta_session=x; vesta_session=abc"),
+        );
+        assert_eq!(get_cookie(&headers, SESSION_COOKIE), Some("abc"));
+        assert_eq!(get_cookie(&headers, OIDC_STATE_COOKIE), None);
+    }
+
+    #[test]
+    fn generated_tokens_are_unique() {
+        let token = generate_token();
+        assert_eq!(token.len(), 43);
+        assert_ne!(token, generate_token());
+    }
+}
@@ -0,0 +1,436 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    auth::session::generate_token,
+    error::{VestaError, VestaResult},
+};
+
+const TOKEN_PREFIX: &str = "vesta_";
+
+/// What an API token is allowed to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenScope {
+    /// Read-only access to the dashboard and API
+    Read,
+    /// Read access plus `POST /api/config/reload`
+    Reload,
+    /// Full administrator access
+    Admin,
+}
+
+impl std::str::FromStr for TokenScope {
+    type Err = VestaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(Self::Read),
+            "reload" => Ok(Self::Reload),
+            "admin" => Ok(Self::Admin),
+            other => Err(VestaError::Internal(format!(
+                "Unknown token scope '{}', expected read, reload or admin",
+                other
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for TokenScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Read => "read",
+            Self::Reload => "reload",
+            Self::Admin => "admin",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ApiToken {
+    pub id: String,
+    pub name: String,
+    pub scope: TokenScope,
+    /// SHA-256 of the token; the token itself is only shown once, when created
+    #[serde(rename = "hash")]
+    pub token_hash: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Default)]
+struct TokenState {
+    tokens: Vec<ApiToken>,
+    /// SHA-256 of the file as last read or written, to notice changes by the CLI
+    digest: Option<[u8; 32]>,
+    dirty: bool,
+}
+
+/// API tokens persisted as JSON next to the configuration file. The server and
+/// the CLI both change the file, each while holding an advisory lock on
+/// `tokens.json.lock`.
+pub struct TokenStore {
+    path: PathBuf,
+    state: RwLock<TokenState>,
+    /// When tokens were last used, by id, until the next sync records it in `state`;
+    /// kept apart so authenticating only needs a read lock
+    usage: Mutex<HashMap<String, DateTime<Utc>>>,
+}
+
+impl TokenStore {
+    pub fn open(path: impl AsRef<Path>) -> VestaResult<Arc<Self>> {
+        let store = Arc::new(Self {
+            path: path.as_ref().to_path_buf(),
+            state: RwLock::new(TokenState::default()),
+            usage: Mutex::new(HashMap::new()),
+        });
+        store.sync()?;
+        Ok(store)
+    }
+
+    /// Periodically persist last-used timestamps and pick up tokens created or
+    /// revoked by the CLI; runs until cancelled
+    pub async fn run_sync(&self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = self.sync() {
+                tracing::error!(error = %e, "Error syncing API tokens");
+            }
+        }
+    }
+
+    /// Look up the token presented by a client and record that it was used
+    pub fn authenticate(&self, token: &str) -> Option<ApiToken> {
+        if !token.starts_with(TOKEN_PREFIX) {
+            return None;
+        }
+
+        let token_hash = hash_token(token);
+        let mut entry = self
+            .state
+            .read()
+            .ok()?
+            .tokens
+            .iter()
+            .find(|entry| entry.token_hash == token_hash)?
+            .clone();
+        let now = Utc::now();
+        entry.last_used_at = Some(now);
+        self.usage.lock().ok()?.insert(entry.id.clone(), now);
+        Some(entry)
+    }
+
+    pub fn list(&self) -> VestaResult<Vec<ApiToken>> {
+        let mut tokens = self.read_state()?.tokens.clone();
+        let usage = self.lock_usage()?;
+        for entry in &mut tokens {
+            if let Some(used) = usage.get(&entry.id) {
+                entry.last_used_at = entry.last_used_at.max(Some(*used));
+            }
+        }
+        Ok(tokens)
+    }
+
+    /// Create a token, returning its metadata and the plaintext token
+    pub fn create(&self, name: &str, scope: TokenScope) -> VestaResult<(ApiToken, String)> {
+        let token = format!("{}{}", TOKEN_PREFIX, generate_token());
+        let entry = ApiToken {
+            id: generate_id(),
+            name: name.to_string(),
+            scope,
+            token_hash: hash_token(&token),
+            created_at: Utc::now(),
+            last_used_at: None,
+        };
+
+        self.update(|tokens| tokens.push(entry.clone()))?;
+        Ok((entry, token))
+    }
+
+    /// Revoke tokens by id or name, returning how many were removed
+    pub fn revoke(&self, id_or_name: &str) -> VestaResult<usize> {
+        self.update(|tokens| {
+            let before = tokens.len();
+            tokens.retain(|entry| entry.id != id_or_name && entry.name != id_or_name);
+            before - tokens.len()
+        })
+    }
+
+    /// Pick up changes made to the file by the CLI and persist last-used timestamps
+    pub fn sync(&self) -> VestaResult<()> {
+        let mut guard = self.write_state()?;
+        let state = &mut *guard;
+        let _lock = self.lock_file()?;
+        self.load_if_changed(state)?;
+        let usage = std::mem::take(&mut *self.lock_usage()?);
+        for entry in &mut state.tokens {
+            if let Some(used) = usage.get(&entry.id) {
+                entry.last_used_at = entry.last_used_at.max(Some(*used));
+                state.dirty = true;
+            }
+        }
+        if state.dirty {
+            self.save(state)?;
+        }
+        Ok(())
+    }
+
+    fn update<R>(&self, f: impl FnOnce(&mut Vec<ApiToken>) -> R) -> VestaResult<R> {
+        let mut state = self.write_state()?;
+        let _lock = self.lock_file()?;
+        self.load_if_changed(&mut state)?;
+        let result = f(&mut state.tokens);
+        self.save(&mut state)?;
+        Ok(result)
+    }
+
+    /// Hold the advisory lock on the token file until the returned file is dropped
+    fn lock_file(&self) -> VestaResult<fs::File> {
+        let mut lock_path = self.path.clone().into_os_string();
+        lock_path.push(".lock");
+        let lock_path = PathBuf::from(lock_path);
+
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .map_err(|e| token_file_error(&lock_path, e))?;
+        file.lock().map_err(|e| token_file_error(&lock_path, e))?;
+        Ok(file)
+    }
+
+    /// Reload the file when its contents changed; file times are too coarse to
+    /// notice a change made right after another
+    fn load_if_changed(&self, state: &mut TokenState) -> VestaResult<()> {
+        let contents = match fs::read(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(token_file_error(&self.path, e)),
+        };
+        let digest: [u8; 32] = Sha256::digest(&contents).into();
+        if state.digest == Some(digest) {
+            return Ok(());
+        }
+
+        let mut tokens: Vec<ApiToken> = serde_json::from_slice(&contents).map_err(|e| {
+            VestaError::Internal(format!("Invalid token file {:?}: {}", self.path, e))
+        })?;
+
+        // Keep usage recorded in memory that has not been written yet
+        let last_used: HashMap<&str, DateTime<Utc>> = state
+            .tokens
+            .iter()
+            .filter_map(|entry| Some((entry.id.as_str(), entry.last_used_at?)))
+            .collect();
+        for entry in &mut tokens {
+            if let Some(used) = last_used.get(entry.id.as_str()) {
+                entry.last_used_at = entry.last_used_at.max(Some(*used));
+            }
+        }
+
+        state.tokens = tokens;
+        state.digest = Some(digest);
+        Ok(())
+    }
+
+    fn save(&self, state: &mut TokenState) -> VestaResult<()> {
+        let contents = serde_json::to_string_pretty(&state.tokens)
+            .map_err(|e| VestaError::Internal(format!("Failed to encode tokens: {}", e)))?;
+        // Written aside and renamed over the file, so a crash or the CLI writing
+        // at the same time never leaves a truncated file behind
+        let temp_path = self
+            .path
+            .with_extension(format!("{}.tmp", std::process::id()));
+        fs::write(&temp_path, &contents).map_err(|e| token_file_error(&temp_path, e))?;
+        fs::rename(&temp_path, &self.path).map_err(|e| token_file_error(&self.path, e))?;
+        state.digest = Some(Sha256::digest(&contents).into());
+        state.dirty = false;
+        Ok(())
+    }
+
+    fn read_state(&self) -> VestaResult<std::sync::RwLockReadGuard<'_, TokenState>> {
+        self.state
+            .read()
+            .map_err(|e| VestaError::Internal(format!("Failed to acquire read lock: {}", e)))
+    }
+
+    fn lock_usage(&self) -> VestaResult<std::sync::MutexGuard<'_, HashMap<String, DateTime<Utc>>>> {
+        self.usage
+            .lock()
+            .map_err(|e| VestaError::Internal(format!("Failed to acquire lock: {}", e)))
+    }
+
+    fn write_state(&self) -> VestaResult<std::sync::RwLockWriteGuard<'_, TokenState>> {
+        self.state
+            .write()
+            .map_err(|e| VestaError::Internal(format!("Failed to acquire write lock: {}", e)))
+    }
+}
+
+fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+fn generate_id() -> String {
+    let mut bytes = [0u8; 6];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn token_file_error(path: &Path, e: std::io::Error) -> VestaError {
+    VestaError::Internal(format!("Failed to access token file {:?}: {}", path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A token file in its own directory, removed when dropped
+    struct TempTokenFile(PathBuf);
+
+    impl TempTokenFile {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("vesta-tokens-{}", generate_token()));
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir.join("tokens.json"))
+        }
+    }
+
+    impl Drop for TempTokenFile {
+        fn drop(&mut self) {
+            if let Some(dir) = self.0.parent() {
+                let _ = fs::remove_dir_all(dir);
+            }
+        }
+    }
+
+    #[test]
+    fn hash_token_is_hex_sha256() {
+        assert_eq!(
+            hash_token("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn created_tokens_authenticate_and_are_stored_hashed() {
+        let file = TempTokenFile::new();
+        let store = TokenStore::open(&file.0).unwrap();
+        let (entry, token) = store.create("ci", TokenScope::Reload).unwrap();
+
+        assert!(token.starts_with(TOKEN_PREFIX));
+        let contents = fs::read_to_string(&file.0).unwrap();
+        assert!(!contents.contains(&token));
+        assert!(contents.contains(&hash_token(&token)));
+
+        let found = store.authenticate(&token).unwrap();
+        assert_eq!(found.id, entry.id);
+        assert_eq!(found.scope, TokenScope::Reload);
+        assert!(found.last_used_at.is_some());
+    }
+
+    #[test]
+    fn unknown_tokens_do_not_authenticate() {
+        let file = TempTokenFile::new();
+        let store = TokenStore::open(&file.0).unwrap();
+        let (_, token) = store.create("ci", TokenScope::Read).unwrap();
+
+        assert!(store.authenticate("vesta_unknown").is_none());
+        assert!(store.authenticate(&token[TOKEN_PREFIX.len()..]).is_none());
+        assert!(store.authenticate("").is_none());
+    }
+
+    #[test]
+    fn revoked_tokens_no_longer_authenticate() {
+        let file = TempTokenFile::new();
+        let store = TokenStore::open(&file.0).unwrap();
+        let (entry, token) = store.create("ci", TokenScope::Admin).unwrap();
+        let (_, other) = store.create("backup", TokenScope::Read).unwrap();
+
+        assert_eq!(store.revoke("unknown").unwrap(), 0);
+        assert_eq!(store.revoke(&entry.id).unwrap(), 1);
+        assert!(store.authenticate(&token).is_none());
+        assert_eq!(store.revoke("backup").unwrap(), 1);
+        assert!(store.authenticate(&other).is_none());
+        assert!(store.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn sync_picks_up_tokens_revoked_elsewhere() {
+        let file = TempTokenFile::new();
+        let server = TokenStore::open(&file.0).unwrap();
+        let (_, token) = server.create("ci", TokenScope::Read).unwrap();
+
+        // The CLI opens the same file in another process, right after the
+        // server wrote it, so the file time is likely not to change
+        let cli = TokenStore::open(&file.0).unwrap();
+        assert_eq!(cli.revoke("ci").unwrap(), 1);
+        server.sync().unwrap();
+
+        assert!(server.authenticate(&token).is_none());
+    }
+
+    #[test]
+    fn sync_does_not_bring_back_tokens_revoked_elsewhere() {
+        let file = TempTokenFile::new();
+        let server = TokenStore::open(&file.0).unwrap();
+        let (_, token) = server.create("ci", TokenScope::Read).unwrap();
+        // Recorded in memory, to be written by the next sync
+        server.authenticate(&token).unwrap();
+
+        let cli = TokenStore::open(&file.0).unwrap();
+        assert_eq!(cli.revoke("ci").unwrap(), 1);
+        server.sync().unwrap();
+
+        let reopened = TokenStore::open(&file.0).unwrap();
+        assert!(reopened.list().unwrap().is_empty());
+        assert!(reopened.authenticate(&token).is_none());
+    }
+
+    #[test]
+    fn concurrent_changes_are_not_lost() {
+        let file = TempTokenFile::new();
+        let stores: Vec<_> = (0..4).map(|_| TokenStore::open(&file.0).unwrap()).collect();
+
+        std::thread::scope(|scope| {
+            for (index, store) in stores.iter().enumerate() {
+                scope.spawn(move || {
+                    for n in 0..10 {
+                        store
+                            .create(&format!("{}-{}", index, n), TokenScope::Read)
+                            .unwrap();
+                    }
+                });
+            }
+        });
+
+        let reopened = TokenStore::open(&file.0).unwrap();
+        assert_eq!(reopened.list().unwrap().len(), 40);
+    }
+
+    #[test]
+    fn sync_persists_when_tokens_were_last_used() {
+        let file = TempTokenFile::new();
+        let store = TokenStore::open(&file.0).unwrap();
+        let (_, token) = store.create("ci", TokenScope::Read).unwrap();
+        let used = store.authenticate(&token).unwrap().last_used_at;
+
+        store.sync().unwrap();
+
+        let reopened = TokenStore::open(&file.0).unwrap();
+        assert_eq!(reopened.list().unwrap()[0].last_used_at, used);
+    }
+}
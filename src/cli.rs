@@ -0,0 +1,132 @@
+use std::io::{self, BufRead, Write};
+
+use crate::{
+    auth::{
+        password::hash_password,
+        tokens::{TokenScope, TokenStore},
+    },
+    config::{DEFAULT_CONFIG_PATH, tokens_path},
+    error::{VestaError, VestaResult},
+};
+
+const USAGE: &str = "Usage: vesta [COMMAND]
+
+Commands:
+  serve                                   Start the dashboard server (default)
+  hash-password                           Read a password from stdin and print its argon2 hash
+  token create <NAME> [--scope <SCOPE>]   Create an API token (scope: read, reload or admin)
+  token list                              List API tokens
+  token revoke <ID|NAME>                  Revoke an API token";
+
+/// Run a command-line subcommand and return its exit code, or `None` when the
+/// server should start
+pub fn run(args: &[String]) -> Option<i32> {
+    match args.get(1).map(String::as_str) {
+        None | Some("serve") => None,
+        Some("hash-password") => Some(hash_password_command()),
+        Some("token") => Some(token_command(&args[2..])),
+        Some("help" | "-h" | "--help") => {
+            println!("{}", USAGE);
+            Some(0)
+        }
+        Some(other) => {
+            eprintln!("Unknown command '{}'\n\n{}", other, USAGE);
+            Some(1)
+        }
+    }
+}
+
+fn hash_password_command() -> i32 {
+    eprint!("Password: ");
+    let _ = io::stderr().flush();
+
+    let mut password = String::new();
+    if let Err(e) = io::stdin().lock().read_line(&mut password) {
+        eprintln!("Failed to read password: {}", e);
+        return 1;
+    }
+    let password = password.trim_end_matches(['\r', '\n']);
+    if password.is_empty() {
+        eprintln!("Password must not be empty");
+        return 1;
+    }
+
+    match hash_password(password) {
+        Ok(hash) => {
+            println!("{}", hash);
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+fn token_command(args: &[String]) -> i32 {
+    let store = match TokenStore::open(tokens_path(DEFAULT_CONFIG_PATH)) {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+
+    let result = match args {
+        [command, name, rest @ ..] if command == "create" => parse_scope(rest)
+            .and_then(|scope| store.create(name, scope))
+            .map(|(token, secret)| {
+                eprintln!(
+                    "Created {} token '{}' with id {}. It will not be shown again:",
+                    token.scope, token.name, token.id
+                );
+                println!("{}", secret);
+            }),
+        [command] if command == "list" => store.list().map(|tokens| {
+            println!(
+                "{:<10} {:<24} {:<8} {:<20} LAST USED",
+                "ID", "NAME", "SCOPE", "CREATED"
+            );
+            for token in tokens {
+                println!(
+                    "{:<10} {:<24} {:<8} {:<20} {}",
+                    token.id,
+                    token.name,
+                    token.scope.to_string(),
+                    token.created_at.format("%Y-%m-%d %H:%M:%S"),
+                    token
+                        .last_used_at
+                        .map(|used| used.format("%Y-%m-%d %H:%M:%S").to_string())
+                        .unwrap_or_else(|| "never".to_string())
+                );
+            }
+        }),
+        [command, id] if command == "revoke" => store.revoke(id).and_then(|removed| {
+            if removed == 0 {
+                return Err(VestaError::Internal(format!("Token '{}' not found", id)));
+            }
+            eprintln!("Revoked {} token(s)", removed);
+            Ok(())
+        }),
+        _ => {
+            eprintln!("{}", USAGE);
+            return 1;
+        }
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+fn parse_scope(args: &[String]) -> VestaResult<TokenScope> {
+    match args {
+        [] => Ok(TokenScope::Read),
+        [flag, scope] if flag == "--scope" => scope.parse(),
+        _ => Err(VestaError::Internal(USAGE.to_string())),
+    }
+}
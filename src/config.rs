@@ -1,28 +1,74 @@
 use indexmap::IndexMap;
-use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fs, path::Path};
+use ipnet::IpNet;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    net::IpAddr,
+    path::{Path, PathBuf},
+};
 
-use crate::error::ConfigError;
+use crate::{auth::Identity, error::ConfigError, render::normalize_base_path};
+
+pub const DEFAULT_CONFIG_PATH: &str = "./config/vesta.toml";
+/// Violet, the accent colour of the built-in stylesheet
+pub const DEFAULT_ACCENT: &str = "#8b5cf6";
+/// Joins the IDs of nested groups, e.g. `admin/network`
+pub const GROUP_PATH_SEPARATOR: char = '/';
+/// Top-level tables that hold settings rather than a group, so no group can use them as its ID
+const RESERVED_GROUP_IDS: &[&str] = &[
+    "server", "logging", "theme", "settings", "search", "networks", "commands", "auth",
+];
+
+/// API tokens are stored next to the config file
+pub fn tokens_path<P: AsRef<Path>>(config_path: P) -> PathBuf {
+    config_path.as_ref().with_file_name("tokens.json")
+}
+
+/// `custom.css` and `custom.js`, added to every page when they exist, live next to the config file
+pub fn custom_asset_path<P: AsRef<Path>>(config_path: P, name: &str) -> PathBuf {
+    config_path.as_ref().with_file_name(name)
+}
+
+/// Cached copies of remote service icons are stored next to the config file
+pub fn icons_path<P: AsRef<Path>>(config_path: P) -> PathBuf {
+    config_path.as_ref().with_file_name("icons")
+}
 
 pub fn load_config<P: AsRef<Path>>(path: P) -> Result<Dashboard, ConfigError> {
     let contents = fs::read_to_string(path)?;
+    let table: toml::Table = toml::from_str(&contents)?;
+    if let Some(id) = reserved_group_id(&table) {
+        return Err(ConfigError::ReservedGroupId(id.to_string()));
+    }
     let parsed: Dashboard = toml::from_str(&contents)?;
+    parsed.validate()?;
     Ok(parsed)
 }
 
+/// A reserved top-level table that is written like a group, with `columns` or
+/// `services`; it would otherwise be read as settings and the group silently dropped
+fn reserved_group_id(table: &toml::Table) -> Option<&str> {
+    RESERVED_GROUP_IDS.iter().copied().find(|id| {
+        table
+            .get(*id)
+            .and_then(toml::Value::as_table)
+            .is_some_and(|group| group.contains_key("columns") || group.contains_key("services"))
+    })
+}
+
 pub fn get_service_info<'a>(
     config: &'a Dashboard,
     group: &str,
     title: &str,
 ) -> Option<&'a Service> {
     config
-        .groups
-        .get(group)
+        .get_group(group)
         .and_then(|group| group.services.iter().find(|service| service.title == title))
 }
 
 pub fn get_widget_info<'a>(config: &'a Dashboard, group: &str, title: &str) -> Option<&'a Widget> {
-    config.groups.get(group).and_then(|group| {
+    config.get_group(group).and_then(|group| {
         group
             .services
             .iter()
@@ -31,9 +77,12 @@ pub fn get_widget_info<'a>(config: &'a Dashboard, group: &str, title: &str) -> O
     })
 }
 
+/// Checks whether a service is up: `url` when given, otherwise the service's href
+/// for `network`, or its default href
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct PingConfig {
-    pub url: String,
+    pub url: Option<String>,
+    pub network: Option<String>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -46,28 +95,743 @@ pub struct Widget {
 pub struct Service {
     pub title: String,
     pub href: Option<String>,
+    /// URLs to use instead of `href` on some networks, keyed by the name of the network
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub hrefs: IndexMap<String, String>,
     #[serde(alias = "imgSrc")]
     pub img_src: Option<String>,
-    pub width: Option<u8>,
-    pub height: Option<u8>,
+    /// Shown below the title and as its tooltip
+    pub description: Option<String>,
+    /// Labels to filter the dashboard by, e.g. `media` or `admin`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Other names the service is found by in search
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// Columns the card spans, e.g. `2` or `{ base = 1, lg = 2 }`
+    pub width: Option<Responsive>,
+    /// Rows the card spans
+    pub height: Option<Responsive>,
     pub widget: Option<Widget>,
     pub ping: Option<PingConfig>,
+    /// Buttons on the card, only shown to administrators
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub actions: Vec<ServiceAction>,
+    #[serde(flatten)]
+    pub visibility: Visibility,
+}
+
+/// Something an administrator can do to a service from its card
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ServiceAction {
+    /// Label of the button, and how `POST /api/service/action` refers to it
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: ActionKind,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ActionKind {
+    /// Send a Wake-on-LAN magic packet to `mac`, by default broadcast to
+    /// `255.255.255.255:9`
+    Wol {
+        mac: String,
+        broadcast: Option<String>,
+    },
+    /// Send an HTTP request, `POST` unless `method` says otherwise
+    Webhook {
+        url: String,
+        method: Option<String>,
+        body: Option<String>,
+    },
+    /// Run one of the commands listed in `[commands]`
+    Command { command: String },
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Group {
     pub name: String,
-    pub columns: u8,
+    /// Columns of the grid, e.g. `4` or `{ base = 1, md = 3, xl = 6 }`
+    pub columns: Responsive,
+    #[serde(default)]
     pub services: Vec<Service>,
+    /// Folded away until opened; each browser then remembers whether it is open
+    #[serde(default)]
+    pub collapsed: bool,
+    /// Child groups, shown as sections below the services, e.g. `[admin.groups.network]`
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub groups: IndexMap<String, Group>,
+    #[serde(flatten)]
+    pub visibility: Visibility,
+}
+
+impl Group {
+    /// Services in this group and all of its child groups
+    pub fn all_services(&self) -> Box<dyn Iterator<Item = &Service> + '_> {
+        Box::new(
+            self.services
+                .iter()
+                .chain(self.groups.values().flat_map(|group| group.all_services())),
+        )
+    }
+
+    /// A copy of the group with the child groups and services hidden from `identity`
+    /// removed, and service actions too unless `identity` is an administrator
+    fn visible_to(&self, identity: &Identity) -> Group {
+        let mut group = self.clone();
+        group
+            .services
+            .retain(|service| service.visibility.is_visible_to(identity));
+        if !identity.is_admin() {
+            for service in &mut group.services {
+                service.actions.clear();
+            }
+        }
+        group.groups = visible_groups(&self.groups, identity);
+        group
+    }
+}
+
+fn visible_groups(
+    groups: &IndexMap<String, Group>,
+    identity: &Identity,
+) -> IndexMap<String, Group> {
+    groups
+        .iter()
+        .filter(|(_, group)| group.visibility.is_visible_to(identity))
+        .map(|(id, group)| (id.clone(), group.visible_to(identity)))
+        .collect()
+}
+
+impl Service {
+    /// The URL to link to for a client on `network`
+    pub fn href_for(&self, network: Option<&str>) -> Option<&str> {
+        network
+            .and_then(|network| self.hrefs.get(network))
+            .or(self.href.as_ref())
+            .map(String::as_str)
+    }
+
+    /// The URL its ping checks, if it has one
+    pub fn ping_url(&self) -> Option<&str> {
+        let ping = self.ping.as_ref()?;
+        ping.url
+            .as_deref()
+            .or_else(|| self.href_for(ping.network.as_deref()))
+    }
+}
+
+/// A network clients can be on, e.g. the LAN at home, for choosing between a service's hrefs
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct NetworkConfig {
+    /// Client addresses on this network, e.g. `192.168.1.0/24`
+    #[serde(default)]
+    pub cidrs: Vec<IpNet>,
+}
+
+/// Screen widths a layout value can change at, as in Tailwind, smallest first
+pub const BREAKPOINTS: [&str; 6] = ["base", "sm", "md", "lg", "xl", "2xl"];
+
+/// A layout value that is either the same on every screen, or changes at
+/// breakpoints. Breakpoints left out keep the value of the next smaller one.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Responsive {
+    Fixed(u8),
+    Breakpoints(BreakpointValues),
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct BreakpointValues {
+    pub base: Option<u8>,
+    pub sm: Option<u8>,
+    pub md: Option<u8>,
+    pub lg: Option<u8>,
+    pub xl: Option<u8>,
+    #[serde(rename = "2xl")]
+    pub xxl: Option<u8>,
+}
+
+impl Responsive {
+    /// The value at each of [`BREAKPOINTS`], with `base` used below the first
+    /// breakpoint when not given
+    pub fn resolve(&self, base: u8) -> [u8; 6] {
+        let values = match *self {
+            Responsive::Fixed(value) => [Some(value); 6],
+            Responsive::Breakpoints(values) => [
+                values.base,
+                values.sm,
+                values.md,
+                values.lg,
+                values.xl,
+                values.xxl,
+            ],
+        };
+
+        let mut resolved = [base; 6];
+        let mut current = base;
+        for (slot, value) in resolved.iter_mut().zip(values) {
+            current = value.unwrap_or(current).max(1);
+            *slot = current;
+        }
+        resolved
+    }
+
+    /// Inline CSS variables for the value at each breakpoint, e.g. `--columns-md: 3`,
+    /// which `style.css` applies with media queries
+    pub fn css_variables(&self, name: &str, base: u8) -> String {
+        BREAKPOINTS
+            .iter()
+            .zip(self.resolve(base))
+            .map(|(breakpoint, value)| format!("--{}-{}: {}", name, breakpoint, value))
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+/// Restricts a group or service to some users; unrestricted when both lists are empty
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Visibility {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_roles: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_users: Vec<String>,
+}
+
+impl Visibility {
+    pub fn is_visible_to(&self, identity: &Identity) -> bool {
+        if identity.is_admin() || (self.allowed_roles.is_empty() && self.allowed_users.is_empty()) {
+            return true;
+        }
+
+        self.allowed_users.contains(&identity.username)
+            || identity
+                .roles
+                .iter()
+                .any(|role| self.allowed_roles.contains(role))
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TlsConfig {
+    /// PEM certificate chain, reloaded automatically when it changes
+    pub cert: PathBuf,
+    /// PEM private key, reloaded automatically when it changes
+    pub key: PathBuf,
+    /// Plain HTTP address that redirects to HTTPS, e.g. `0.0.0.0:80`
+    pub redirect_address: Option<String>,
+}
+
+/// Settings for the HTTP server itself; changes need a restart
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ServerConfig {
+    #[serde(default = "ServerConfig::default_address")]
+    pub address: String,
+    /// Addresses to listen on instead of `address`: `host:port`, `[::]:port` or
+    /// `unix:/path/to/socket`
+    #[serde(default)]
+    pub listen: Vec<String>,
+    /// Permissions for Unix sockets, e.g. `0o660`
+    pub socket_mode: Option<u32>,
+    /// Path prefix to serve Vesta under, e.g. `/dash`
+    #[serde(default, deserialize_with = "deserialize_base_path")]
+    pub base_path: String,
+    pub tls: Option<TlsConfig>,
+    /// Peers whose forwarding headers (e.g. `Remote-User` or `X-Forwarded-For`) are trusted
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpNet>,
+    /// Serve `/static` from this directory instead of the assets built into the
+    /// binary, e.g. while working on the CSS
+    pub assets_dir: Option<PathBuf>,
+    /// Send a Content-Security-Policy that only allows resources from Vesta itself
+    #[serde(default)]
+    pub strict_csp: bool,
+    /// Header in which a trusted proxy names the client's network, e.g. `X-Vesta-Network: home`
+    pub network_header: Option<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            address: Self::default_address(),
+            listen: Vec::new(),
+            socket_mode: None,
+            base_path: String::new(),
+            tls: None,
+            trusted_proxies: Vec::new(),
+            assets_dir: None,
+            strict_csp: false,
+            network_header: None,
+        }
+    }
+}
+
+impl ServerConfig {
+    fn default_address() -> String {
+        "0.0.0.0:3000".to_string()
+    }
+
+    pub fn listen_addresses(&self) -> Vec<&str> {
+        if self.listen.is_empty() {
+            vec![self.address.as_str()]
+        } else {
+            self.listen.iter().map(String::as_str).collect()
+        }
+    }
+
+    /// IPv4 peers of a dual-stack socket show up as IPv4-mapped IPv6 addresses,
+    /// so those are matched as the IPv4 address they stand for
+    pub fn is_trusted_proxy(&self, addr: IpAddr) -> bool {
+        let addr = addr.to_canonical();
+        self.trusted_proxies.iter().any(|net| net.contains(&addr))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human readable lines
+    #[default]
+    Text,
+    /// One JSON object per line, for log collectors
+    Json,
+}
+
+/// Log output settings; changes need a restart
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LoggingConfig {
+    /// Level or filter directives, e.g. `debug` or `info,vesta=debug`.
+    /// `RUST_LOG` takes precedence when set.
+    #[serde(default = "LoggingConfig::default_level")]
+    pub level: String,
+    #[serde(default)]
+    pub format: LogFormat,
+    /// OTLP/HTTP endpoint to export traces to, e.g. `http://localhost:4318/v1/traces`.
+    /// Requires building with the `otel` feature.
+    pub otlp_endpoint: Option<String>,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: Self::default_level(),
+            format: LogFormat::default(),
+            otlp_endpoint: None,
+        }
+    }
+}
+
+impl LoggingConfig {
+    fn default_level() -> String {
+        "info".to_string()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeMode {
+    #[default]
+    Dark,
+    Light,
+    /// Follow the browser's `prefers-color-scheme`
+    Auto,
+}
+
+impl ThemeMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ThemeMode::Dark => "dark",
+            ThemeMode::Light => "light",
+            ThemeMode::Auto => "auto",
+        }
+    }
+}
+
+/// Look of the dashboard, applied through CSS variables
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ThemeConfig {
+    #[serde(default)]
+    pub mode: ThemeMode,
+    /// Any CSS colour, e.g. `#10b981`; shades are derived from it. Violet when unset.
+    pub accent: Option<String>,
+    /// URL of an image to show behind the dashboard
+    pub background: Option<String>,
+    /// Blur radius for the background image, in pixels
+    #[serde(default)]
+    pub background_blur: u8,
+    /// Opacity of cards in percent, to let the background show through
+    #[serde(default = "ThemeConfig::default_card_opacity")]
+    pub card_opacity: u8,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            mode: ThemeMode::default(),
+            accent: None,
+            background: None,
+            background_blur: 0,
+            card_opacity: Self::default_card_opacity(),
+        }
+    }
+}
+
+impl ThemeConfig {
+    fn default_card_opacity() -> u8 {
+        100
+    }
+
+    /// The accent colour, unless it could break out of its CSS declaration
+    pub fn accent(&self) -> Option<&str> {
+        self.accent
+            .as_deref()
+            .filter(|value| is_safe_css_value(value))
+    }
+
+    /// CSS custom properties for the root element. Values that could break out
+    /// of their declaration are left out.
+    pub fn css_variables(&self) -> String {
+        let mut variables = vec![
+            format!("--card-opacity: {}%", self.card_opacity.min(100)),
+            format!("--background-blur: {}px", self.background_blur),
+        ];
+        if let Some(accent) = self.accent() {
+            variables.push(format!("--accent: {}", accent));
+        }
+        if let Some(background) = self
+            .background
+            .as_deref()
+            .filter(|value| is_safe_css_value(value))
+        {
+            variables.push(format!("--background-image: url(\"{}\")", background));
+        }
+        variables.join("; ")
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FooterLink {
+    pub title: String,
+    pub url: String,
+}
+
+/// Branding shown across the pages
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SettingsConfig {
+    /// Title of the browser tab
+    pub title: String,
+    /// Name at the top of the sidebar and the login form
+    pub wordmark: String,
+    /// Smaller text next to the wordmark; hidden when empty
+    pub tagline: String,
+    /// Heading above the groups
+    pub header: String,
+    /// Line below the heading; hidden when empty
+    pub subtitle: String,
+    /// Icon for the browser tab, a URL or icon shorthand; Vesta's own when unset
+    pub favicon: Option<String>,
+    /// Image shown in front of the wordmark, a URL or icon shorthand
+    pub logo: Option<String>,
+    /// Links shown below the groups
+    pub footer_links: Vec<FooterLink>,
+    /// Language of the interface, `en`, `de` or `id`; picked from the browser's
+    /// languages when unset
+    pub locale: Option<String>,
+}
+
+impl Default for SettingsConfig {
+    fn default() -> Self {
+        Self {
+            title: "Vesta".to_string(),
+            wordmark: "vesta".to_string(),
+            tagline: "dashboard".to_string(),
+            header: "Services".to_string(),
+            subtitle: "Self-hosted applications".to_string(),
+            favicon: None,
+            logo: None,
+            footer_links: Vec::new(),
+            locale: None,
+        }
+    }
+}
+
+/// A web search engine for the command palette
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SearchProvider {
+    pub name: String,
+    /// Search URL with `{query}` where the search terms go
+    pub url: String,
+    /// Prefix that picks this provider, e.g. `g` for `!g rust`
+    pub bang: Option<String>,
+}
+
+impl SearchProvider {
+    fn new(name: &str, url: &str, bang: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            url: url.to_string(),
+            bang: Some(bang.to_string()),
+        }
+    }
+}
+
+/// Web search offered by the command palette when no service matches
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct SearchConfig {
+    /// The first provider is used when no `!bang` is given. Defaults to
+    /// DuckDuckGo, Google and Wikipedia.
+    #[serde(default)]
+    pub providers: Vec<SearchProvider>,
+}
+
+impl SearchConfig {
+    pub fn providers(&self) -> Vec<SearchProvider> {
+        if !self.providers.is_empty() {
+            return self.providers.clone();
+        }
+        vec![
+            SearchProvider::new("DuckDuckGo", "https://duckduckgo.com/?q={query}", "d"),
+            SearchProvider::new("Google", "https://www.google.com/search?q={query}", "g"),
+            SearchProvider::new(
+                "Wikipedia",
+                "https://en.wikipedia.org/w/index.php?search={query}",
+                "w",
+            ),
+        ]
+    }
+}
+
+/// Whether `url` is served by Vesta, e.g. `/static/bg.jpg`, or inline `data:`
+fn is_local_url(url: &str) -> bool {
+    url.starts_with("data:") || (!url.starts_with("//") && reqwest::Url::parse(url).is_err())
+}
+
+fn is_safe_css_value(value: &str) -> bool {
+    !value
+        .chars()
+        .any(|c| matches!(c, ';' | '{' | '}' | '<' | '>' | '"' | '\'' | '\\') || c.is_control())
+}
+
+fn deserialize_base_path<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    String::deserialize(deserializer).map(|path| normalize_base_path(&path))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthMethod {
+    /// Login form backed by `[[auth.users]]`, remembered with a session cookie
+    Session,
+    /// HTTP basic auth against `[[auth.users]]`
+    Basic,
+    /// Identity supplied by a trusted reverse proxy (Authelia, Authentik, ...)
+    Proxy,
+    /// OpenID Connect authorization code flow with PKCE, remembered with a session cookie
+    Oidc,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct UserConfig {
+    pub username: String,
+    /// Argon2 PHC string, as printed by `vesta hash-password`
+    pub password_hash: String,
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ProxyAuthConfig {
+    #[serde(default = "ProxyAuthConfig::default_user_header")]
+    pub user_header: String,
+    #[serde(default = "ProxyAuthConfig::default_groups_header")]
+    pub groups_header: String,
+    /// Roles given to proxy users whose groups header is missing or empty
+    #[serde(default)]
+    pub default_roles: Vec<String>,
+}
+
+impl ProxyAuthConfig {
+    fn default_user_header() -> String {
+        "Remote-User".to_string()
+    }
+
+    fn default_groups_header() -> String {
+        "Remote-Groups".to_string()
+    }
+}
+
+impl Default for ProxyAuthConfig {
+    fn default() -> Self {
+        Self {
+            user_header: Self::default_user_header(),
+            groups_header: Self::default_groups_header(),
+            default_roles: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OidcConfig {
+    /// Issuer URL, used for discovery via `/.well-known/openid-configuration`
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    /// Absolute URL of `/auth/oidc/callback` as registered with the provider
+    pub redirect_url: String,
+    #[serde(default = "OidcConfig::default_provider_name")]
+    pub provider_name: String,
+    #[serde(default = "OidcConfig::default_scopes")]
+    pub scopes: Vec<String>,
+    /// Claim matched against `allowed_users` and shown as the user's name. Only use a
+    /// claim users can't change themselves, such as `preferred_username` on providers
+    /// where administrators set it.
+    #[serde(default = "OidcConfig::default_username_claim")]
+    pub username_claim: String,
+    /// Claim holding the user's groups, either an array or a comma-separated string
+    #[serde(default = "OidcConfig::default_roles_claim")]
+    pub roles_claim: String,
+    /// Map provider groups to Vesta roles; unmapped groups are used as-is
+    #[serde(default)]
+    pub role_mapping: HashMap<String, String>,
+    /// Where the provider should send the browser after logging out
+    pub post_logout_redirect_url: Option<String>,
+}
+
+impl OidcConfig {
+    fn default_provider_name() -> String {
+        "OpenID Connect".to_string()
+    }
+
+    fn default_scopes() -> Vec<String> {
+        ["openid", "profile", "email", "groups"]
+            .into_iter()
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn default_username_claim() -> String {
+        "sub".to_string()
+    }
+
+    fn default_roles_claim() -> String {
+        "groups".to_string()
+    }
+
+    pub fn map_roles(&self, groups: Vec<String>) -> Vec<String> {
+        groups
+            .into_iter()
+            .map(|group| self.role_mapping.get(&group).cloned().unwrap_or(group))
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AuthConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "AuthConfig::default_methods")]
+    pub methods: Vec<AuthMethod>,
+    /// Session lifetime in seconds
+    #[serde(default = "AuthConfig::default_session_ttl")]
+    pub session_ttl: u64,
+    /// Roles allowed to view the dashboard; empty means any authenticated user
+    #[serde(default)]
+    pub viewer_roles: Vec<String>,
+    /// Roles allowed to administer Vesta (reload config, read widget credentials)
+    #[serde(default = "AuthConfig::default_admin_roles")]
+    pub admin_roles: Vec<String>,
+    #[serde(default)]
+    pub users: Vec<UserConfig>,
+    #[serde(default)]
+    pub proxy: ProxyAuthConfig,
+    pub oidc: Option<OidcConfig>,
+}
+
+impl AuthConfig {
+    fn default_methods() -> Vec<AuthMethod> {
+        vec![AuthMethod::Session]
+    }
+
+    fn default_session_ttl() -> u64 {
+        60 * 60 * 24 * 7
+    }
+
+    fn default_admin_roles() -> Vec<String> {
+        vec!["admin".to_string()]
+    }
+
+    pub fn has_method(&self, method: AuthMethod) -> bool {
+        self.methods.contains(&method)
+    }
+
+    /// The OIDC settings, if the `oidc` method is enabled
+    pub fn oidc(&self) -> Option<&OidcConfig> {
+        self.oidc
+            .as_ref()
+            .filter(|_| self.has_method(AuthMethod::Oidc))
+    }
+
+    pub fn get_user(&self, username: &str) -> Option<&UserConfig> {
+        self.users.iter().find(|user| user.username == username)
+    }
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            methods: Self::default_methods(),
+            session_ttl: Self::default_session_ttl(),
+            viewer_roles: Vec::new(),
+            admin_roles: Self::default_admin_roles(),
+            users: Vec::new(),
+            proxy: ProxyAuthConfig::default(),
+            oidc: None,
+        }
+    }
 }
 
 #[derive(Clone, Deserialize, Serialize)]
 pub struct Dashboard {
+    #[serde(default)]
+    pub server: ServerConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
+    pub theme: ThemeConfig,
+    #[serde(default)]
+    pub settings: SettingsConfig,
+    #[serde(default)]
+    pub search: SearchConfig,
+    #[serde(default)]
+    pub networks: IndexMap<String, NetworkConfig>,
+    /// Local commands that service actions may run, by name, e.g.
+    /// `backup = ["/usr/local/bin/backup", "--now"]`
+    #[serde(default, skip_serializing)]
+    pub commands: IndexMap<String, Vec<String>>,
+    #[serde(default, skip_serializing)]
+    pub auth: AuthConfig,
     #[serde(flatten)]
     pub groups: IndexMap<String, Group>,
 }
 
 impl Dashboard {
+    /// Settings that parse but can't work together
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.server.strict_csp
+            && let Some(background) = self.theme.background.as_deref()
+            && !is_local_url(background)
+        {
+            return Err(ConfigError::Invalid(format!(
+                "theme.background {:?} would be blocked by server.strict_csp; serve it from assets_dir instead, e.g. \"/static/bg.jpg\"",
+                background
+            )));
+        }
+        Ok(())
+    }
+
     pub fn get_service(&self, group: &str, title: &str) -> Option<&Service> {
         get_service_info(self, group, title)
     }
@@ -75,4 +839,177 @@ impl Dashboard {
     pub fn get_widget(&self, group: &str, title: &str) -> Option<&Widget> {
         get_widget_info(self, group, title)
     }
+
+    /// The network a client is on: the one a trusted proxy names in
+    /// `server.network_header`, or else the first whose CIDRs contain `client`
+    pub fn network_for(&self, named: Option<&str>, client: Option<IpAddr>) -> Option<&str> {
+        if let Some((name, _)) = named.and_then(|name| self.networks.get_key_value(name)) {
+            return Some(name);
+        }
+        let client = client?.to_canonical();
+        self.networks
+            .iter()
+            .find(|(_, network)| network.cidrs.iter().any(|cidr| cidr.contains(&client)))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Look up a group by its path, e.g. `admin/network` for the child group
+    /// `network` of `admin`
+    pub fn get_group(&self, path: &str) -> Option<&Group> {
+        self.group_path(path)
+            .and_then(|groups| groups.last().copied())
+    }
+
+    /// The groups along `path`, outermost first
+    fn group_path(&self, path: &str) -> Option<Vec<&Group>> {
+        let mut groups: Vec<&Group> = Vec::new();
+        for id in path.split(GROUP_PATH_SEPARATOR) {
+            let children = groups.last().map_or(&self.groups, |group| &group.groups);
+            groups.push(children.get(id)?);
+        }
+        Some(groups)
+    }
+
+    /// Every group, child groups right after their parent, each with its path
+    pub fn all_groups(&self) -> Vec<(String, &Group)> {
+        fn collect<'a>(
+            groups: &'a IndexMap<String, Group>,
+            parent: Option<&str>,
+            all: &mut Vec<(String, &'a Group)>,
+        ) {
+            for (id, group) in groups {
+                let path = match parent {
+                    Some(parent) => format!("{}{}{}", parent, GROUP_PATH_SEPARATOR, id),
+                    None => id.clone(),
+                };
+                all.push((path.clone(), group));
+                collect(&group.groups, Some(&path), all);
+            }
+        }
+
+        let mut all = Vec::new();
+        collect(&self.groups, None, &mut all);
+        all
+    }
+
+    /// Services in every group, including child groups
+    pub fn all_services(&self) -> impl Iterator<Item = &Service> {
+        self.groups.values().flat_map(|group| group.all_services())
+    }
+
+    /// Look up a service, treating services hidden from `identity` as missing
+    pub fn get_visible_service(
+        &self,
+        group: &str,
+        title: &str,
+        identity: &Identity,
+    ) -> Option<&Service> {
+        self.group_path(group)
+            .filter(|groups| {
+                groups
+                    .iter()
+                    .all(|group| group.visibility.is_visible_to(identity))
+            })
+            .and_then(|groups| groups.last().copied())
+            .and_then(|group| group.services.iter().find(|service| service.title == title))
+            .filter(|service| service.visibility.is_visible_to(identity))
+    }
+
+    /// All tags used by services, in the order they first appear
+    pub fn tags(&self) -> Vec<&str> {
+        let mut tags: Vec<&str> = Vec::new();
+        for service in self.all_services() {
+            for tag in &service.tags {
+                if !tags.contains(&tag.as_str()) {
+                    tags.push(tag);
+                }
+            }
+        }
+        tags
+    }
+
+    /// A copy of the dashboard with the groups and services hidden from `identity`
+    /// removed, and service actions too unless `identity` is an administrator
+    pub fn visible_to(&self, identity: &Identity) -> Dashboard {
+        let groups = visible_groups(&self.groups, identity);
+
+        Dashboard {
+            server: self.server.clone(),
+            logging: self.logging.clone(),
+            theme: self.theme.clone(),
+            settings: self.settings.clone(),
+            search: self.search.clone(),
+            networks: self.networks.clone(),
+            commands: self.commands.clone(),
+            auth: self.auth.clone(),
+            groups,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_urls_are_paths_and_data() {
+        for url in ["/static/bg.jpg", "bg.jpg", "data:image/png;base64,AAAA"] {
+            assert!(is_local_url(url), "{url}");
+        }
+        for url in [
+            "https://example.com/bg.jpg",
+            "//example.com/bg.jpg",
+            "http:bg.jpg",
+        ] {
+            assert!(!is_local_url(url), "{url}");
+        }
+    }
+
+    #[test]
+    fn strict_csp_rejects_remote_backgrounds() {
+        let config = |background: &str| {
+            toml::from_str::<Dashboard>(&format!(
+                "[server]\nstrict_csp = true\n[theme]\nbackground = {:?}",
+                background
+            ))
+            .unwrap()
+        };
+        assert!(config("/static/bg.jpg").validate().is_ok());
+        assert!(matches!(
+            config("https://example.com/bg.jpg").validate(),
+            Err(ConfigError::Invalid(_))
+        ));
+    }
+
+    fn networks() -> Dashboard {
+        toml::from_str(
+            r#"
+            [networks.vpn]
+            cidrs = ["10.8.0.0/24"]
+            [networks.home]
+            cidrs = ["10.0.0.0/8", "fd00::/8"]
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn network_for_picks_the_first_matching_network() {
+        let config = networks();
+        let ip = |ip: &str| Some(ip.parse().unwrap());
+        assert_eq!(config.network_for(None, ip("10.8.0.5")), Some("vpn"));
+        assert_eq!(config.network_for(None, ip("10.1.2.3")), Some("home"));
+        assert_eq!(config.network_for(None, ip("fd00::1")), Some("home"));
+        assert_eq!(config.network_for(None, ip("::ffff:10.8.0.5")), Some("vpn"));
+        assert_eq!(config.network_for(None, ip("192.168.1.1")), None);
+        assert_eq!(config.network_for(None, None), None);
+    }
+
+    #[test]
+    fn network_for_prefers_a_named_network_that_exists() {
+        let config = networks();
+        let client = Some("10.8.0.5".parse().unwrap());
+        assert_eq!(config.network_for(Some("home"), client), Some("home"));
+        assert_eq!(config.network_for(Some("office"), client), Some("vpn"));
+    }
 }
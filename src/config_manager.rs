@@ -3,8 +3,11 @@ use std::sync::{Arc, RwLock, RwLockReadGuard};
 use tokio::sync::broadcast;
 
 use crate::{
-    config::{Dashboard, load_config},
+    auth::Identity,
+    config::{Dashboard, custom_asset_path, load_config},
     error::{VestaError, VestaResult},
+    icons::IconIndex,
+    render::{CUSTOM_CSS, CUSTOM_JS},
     widget_system::WidgetRegistry,
 };
 
@@ -21,9 +24,28 @@ pub enum ConfigChanges {
     WidgetsUpdated,
 }
 
+/// Which of `custom.css` and `custom.js` exist next to the config file, checked
+/// when the config is loaded so that requests don't touch the file system
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CustomAssets {
+    pub css: bool,
+    pub js: bool,
+}
+
+impl CustomAssets {
+    fn find(config_path: &str) -> Self {
+        Self {
+            css: custom_asset_path(config_path, CUSTOM_CSS).is_file(),
+            js: custom_asset_path(config_path, CUSTOM_JS).is_file(),
+        }
+    }
+}
+
 /// Configuration manager that handles config access, validation, and change notifications
 pub struct ConfigManager {
     config: Arc<RwLock<Dashboard>>,
+    custom_assets: RwLock<CustomAssets>,
+    icon_index: RwLock<Arc<IconIndex>>,
     config_path: String,
     change_notifier: broadcast::Sender<ConfigChangeEvent>,
     widget_registry: Arc<WidgetRegistry>,
@@ -37,8 +59,12 @@ impl ConfigManager {
 
         let (change_notifier, _) = broadcast::channel(100);
 
+        let icon_index = Arc::new(IconIndex::new(&config));
+
         Ok(Self {
             config: Arc::new(RwLock::new(config)),
+            custom_assets: RwLock::new(CustomAssets::find(config_path)),
+            icon_index: RwLock::new(icon_index),
             config_path: config_path.to_string(),
             change_notifier,
             widget_registry,
@@ -51,6 +77,24 @@ impl ConfigManager {
             .map_err(|e| VestaError::Internal(format!("Failed to acquire read lock: {}", e)))
     }
 
+    pub fn custom_assets(&self) -> CustomAssets {
+        self.custom_assets
+            .read()
+            .map(|custom_assets| *custom_assets)
+            .unwrap_or_default()
+    }
+
+    pub fn icon_index(&self) -> Arc<IconIndex> {
+        self.icon_index
+            .read()
+            .map(|icon_index| icon_index.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn config_path(&self) -> &str {
+        &self.config_path
+    }
+
     pub fn get_config(&self) -> VestaResult<Dashboard> {
         let config = self.read_config()?;
         Ok(config.clone())
@@ -60,6 +104,7 @@ impl ConfigManager {
         let new_config = load_config(&self.config_path)?;
 
         self.widget_registry.validate_widgets(&new_config)?;
+        let icon_index = Arc::new(IconIndex::new(&new_config));
 
         {
             let mut config = self.config.write().map_err(|e| {
@@ -67,6 +112,12 @@ impl ConfigManager {
             })?;
             *config = new_config;
         }
+        if let Ok(mut custom_assets) = self.custom_assets.write() {
+            *custom_assets = CustomAssets::find(&self.config_path);
+        }
+        if let Ok(mut index) = self.icon_index.write() {
+            *index = icon_index;
+        }
 
         let _ = self.change_notifier.send(ConfigChangeEvent {
             timestamp: std::time::SystemTime::now(),
@@ -89,6 +140,16 @@ impl ConfigManager {
         Ok(config.get_service(group, title).cloned())
     }
 
+    pub fn get_visible_service(
+        &self,
+        group: &str,
+        title: &str,
+        identity: &Identity,
+    ) -> VestaResult<Option<crate::config::Service>> {
+        let config = self.read_config()?;
+        Ok(config.get_visible_service(group, title, identity).cloned())
+    }
+
     pub fn get_widget(
         &self,
         group: &str,
@@ -106,22 +167,12 @@ impl ConfigManager {
     pub fn get_config_stats(&self) -> VestaResult<ConfigStats> {
         let config = self.read_config()?;
 
-        let total_services = config.groups.values().map(|g| g.services.len()).sum();
-        let services_with_ping = config
-            .groups
-            .values()
-            .flat_map(|g| &g.services)
-            .filter(|s| s.ping.is_some())
-            .count();
-        let services_with_widgets = config
-            .groups
-            .values()
-            .flat_map(|g| &g.services)
-            .filter(|s| s.widget.is_some())
-            .count();
+        let total_services = config.all_services().count();
+        let services_with_ping = config.all_services().filter(|s| s.ping.is_some()).count();
+        let services_with_widgets = config.all_services().filter(|s| s.widget.is_some()).count();
 
         Ok(ConfigStats {
-            total_groups: config.groups.len(),
+            total_groups: config.all_groups().len(),
             total_services,
             services_with_ping,
             services_with_widgets,
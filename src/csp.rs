@@ -0,0 +1,66 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension,
+    extract::Request,
+    http::{HeaderValue, header},
+    middleware::Next,
+    response::Response,
+};
+use reqwest::Url;
+
+use crate::{AppState, config::Dashboard};
+
+/// Add a Content-Security-Policy to every response when `server.strict_csp` is set
+pub async fn content_security_policy(
+    Extension(state): Extension<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let policy = match state.get_config_manager().read_config() {
+        Ok(config) if config.server.strict_csp => Some(strict_policy(&config)),
+        _ => None,
+    };
+
+    let mut response = next.run(request).await;
+    if let Some(policy) = policy
+        && let Ok(value) = HeaderValue::from_str(&policy)
+    {
+        response
+            .headers_mut()
+            .insert(header::CONTENT_SECURITY_POLICY, value);
+    }
+    response
+}
+
+/// A policy without any external origins. Service icons therefore have to be
+/// served by Vesta itself, and inline `style` attributes are the only exception,
+/// for sizes computed on the server.
+fn strict_policy(config: &Dashboard) -> String {
+    // Logging out redirects the logout form to the identity provider
+    let form_action = match config.auth.oidc().and_then(|oidc| origin(&oidc.issuer)) {
+        Some(issuer) => format!("'self' {}", issuer),
+        None => "'self'".to_string(),
+    };
+
+    [
+        "default-src 'self'".to_string(),
+        "script-src 'self'".to_string(),
+        "style-src 'self'".to_string(),
+        "style-src-attr 'unsafe-inline'".to_string(),
+        "img-src 'self' data:".to_string(),
+        "font-src 'self'".to_string(),
+        "connect-src 'self'".to_string(),
+        "object-src 'none'".to_string(),
+        "base-uri 'none'".to_string(),
+        "frame-ancestors 'none'".to_string(),
+        format!("form-action {}", form_action),
+    ]
+    .join("; ")
+}
+
+fn origin(url: &str) -> Option<String> {
+    Url::parse(url)
+        .ok()
+        .map(|url| url.origin().ascii_serialization())
+}
@@ -26,6 +26,12 @@ pub enum VestaError {
     #[error("Missing credentials: {field}")]
     MissingCredentials { field: String },
 
+    #[error("Authentication required")]
+    Unauthorized,
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
     #[error("API error: {status} - {message}")]
     ApiError { status: StatusCode, message: String },
 
@@ -39,6 +45,12 @@ pub enum ConfigError {
     IoError(#[from] std::io::Error),
     #[error("Failed to parse TOML: {0}")]
     TomlError(#[from] toml::de::Error),
+    #[error(
+        "`[{0}]` is reserved for dashboard settings and can't be used as a group ID; rename the group, e.g. `[{0}_group]`"
+    )]
+    ReservedGroupId(String),
+    #[error("Invalid configuration: {0}")]
+    Invalid(String),
 }
 
 impl IntoResponse for VestaError {
@@ -54,6 +66,8 @@ impl IntoResponse for VestaError {
             VestaError::MissingCredentials { .. } => {
                 (StatusCode::BAD_REQUEST, "Missing credentials")
             }
+            VestaError::Unauthorized => (StatusCode::UNAUTHORIZED, "Authentication required"),
+            VestaError::Forbidden(_) => (StatusCode::FORBIDDEN, "Forbidden"),
             VestaError::ApiError { status, .. } => (*status, "API error"),
             VestaError::Internal(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error"),
         };
@@ -1,5 +1,6 @@
-use reqwest::Client;
-use std::time::Duration;
+use reqwest::{Client, RequestBuilder, Response};
+use std::time::{Duration, Instant};
+use tracing::{Instrument, field};
 
 use crate::error::{VestaError, VestaResult};
 
@@ -44,3 +45,34 @@ impl HttpClientBuilder {
 pub fn create_default_client() -> VestaResult<Client> {
     HttpClientBuilder::default().build()
 }
+
+/// Send an outbound request inside a span recording its method, URL, status and
+/// duration. The query string is left out of the span, since it may hold API keys.
+pub async fn send(request: RequestBuilder) -> reqwest::Result<Response> {
+    let (client, request) = request.build_split();
+    let request = request?;
+
+    let mut url = request.url().clone();
+    url.set_query(None);
+    let span = tracing::info_span!(
+        "http_client",
+        method = %request.method(),
+        url = %url,
+        status = field::Empty,
+    );
+
+    let started = Instant::now();
+    let result = client.execute(request).instrument(span.clone()).await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+    let _entered = span.enter();
+    match &result {
+        Ok(response) => {
+            let status = response.status().as_u16();
+            span.record("status", status);
+            tracing::debug!(status, latency_ms, "Outbound request finished");
+        }
+        // Callers decide whether a failure is worth reporting, e.g. a ping to a host that is down
+        Err(e) => tracing::debug!(error = %e, latency_ms, "Outbound request failed"),
+    }
+    result
+}
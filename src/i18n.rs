@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use chrono::{Datelike, Timelike};
+
+/// Catalogs built into the binary, by language code. The first is the fallback
+/// for strings missing from the others.
+const CATALOG_SOURCES: [(&str, &str); 3] = [
+    ("en", include_str!("../locales/en.toml")),
+    ("de", include_str!("../locales/de.toml")),
+    ("id", include_str!("../locales/id.toml")),
+];
+
+static CATALOGS: LazyLock<Vec<Catalog>> = LazyLock::new(|| {
+    CATALOG_SOURCES
+        .iter()
+        .map(|(code, source)| Catalog::parse(code, source))
+        .collect()
+});
+
+struct Catalog {
+    code: &'static str,
+    /// Strings keyed by their dotted path, e.g. `sidebar.groups`. The items of
+    /// lists are keyed by index, e.g. `date.months.0`.
+    messages: HashMap<String, String>,
+}
+
+impl Catalog {
+    fn parse(code: &'static str, source: &str) -> Self {
+        // The catalogs are part of the source, so a broken one is a bug
+        let table: toml::Table = toml::from_str(source)
+            .unwrap_or_else(|e| panic!("Invalid catalog locales/{}.toml: {}", code, e));
+        let mut messages = HashMap::new();
+        flatten("", &toml::Value::Table(table), &mut messages);
+        Self { code, messages }
+    }
+}
+
+fn flatten(prefix: &str, value: &toml::Value, messages: &mut HashMap<String, String>) {
+    let key = |name: &dyn std::fmt::Display| {
+        if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", prefix, name)
+        }
+    };
+    match value {
+        toml::Value::Table(table) => {
+            for (name, value) in table {
+                flatten(&key(name), value, messages);
+            }
+        }
+        toml::Value::Array(items) => {
+            for (index, value) in items.iter().enumerate() {
+                flatten(&key(&index), value, messages);
+            }
+        }
+        toml::Value::String(message) => {
+            messages.insert(prefix.to_string(), message.clone());
+        }
+        other => {
+            messages.insert(prefix.to_string(), other.to_string());
+        }
+    }
+}
+
+/// The language of the interface, with its strings and number and date formats
+#[derive(Clone, Copy)]
+pub struct Locale {
+    catalog: &'static Catalog,
+}
+
+impl std::fmt::Debug for Locale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Locale").field(&self.catalog.code).finish()
+    }
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self {
+            catalog: &CATALOGS[0],
+        }
+    }
+}
+
+impl Locale {
+    /// The locale for a language tag such as `de` or `de-AT`, if there is a catalog for it
+    pub fn find(tag: &str) -> Option<Self> {
+        let language = tag.trim().split(['-', '_']).next()?;
+        CATALOGS
+            .iter()
+            .find(|catalog| catalog.code.eq_ignore_ascii_case(language))
+            .map(|catalog| Self { catalog })
+    }
+
+    /// The most preferred locale of an `Accept-Language` header that has a catalog,
+    /// e.g. `de` for `fr;q=0.9, de;q=0.8`
+    pub fn negotiate(accept_language: &str) -> Option<Self> {
+        let mut preferences: Vec<(&str, f32)> = accept_language
+            .split(',')
+            .filter_map(|entry| {
+                let mut parts = entry.split(';');
+                let tag = parts.next()?.trim();
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .and_then(|q| q.parse().ok())
+                    .unwrap_or(1.0);
+                Some((tag, quality))
+            })
+            .filter(|(tag, quality)| !tag.is_empty() && *tag != "*" && *quality > 0.0)
+            .collect();
+        // Stable, so equally preferred languages keep the order of the header
+        preferences.sort_by(|a, b| b.1.total_cmp(&a.1));
+        preferences.into_iter().find_map(|(tag, _)| Self::find(tag))
+    }
+
+    /// Language code, e.g. for the `lang` attribute
+    pub fn code(&self) -> &'static str {
+        self.catalog.code
+    }
+
+    /// The string for `key`, in English when this catalog lacks it, or the key
+    /// itself when no catalog has it
+    pub fn t<'a>(&self, key: &'a str) -> &'a str {
+        self.catalog
+            .messages
+            .get(key)
+            .or_else(|| CATALOGS[0].messages.get(key))
+            .map(String::as_str)
+            .unwrap_or_else(|| {
+                tracing::warn!(key, locale = self.code(), "Missing translation");
+                key
+            })
+    }
+
+    /// The string for `key` with each `{name}` replaced by its value in `args`
+    pub fn t_with(&self, key: &str, args: &[(&str, &str)]) -> String {
+        args.iter()
+            .fold(self.t(key).to_string(), |message, (name, value)| {
+                message.replace(&format!("{{{}}}", name), value)
+            })
+    }
+
+    /// `value` with `decimals` digits after the separator and grouped thousands,
+    /// e.g. `1.234,5` in German
+    pub fn number(&self, value: f64, decimals: usize) -> String {
+        let formatted = format!("{:.*}", decimals, value);
+        let (sign, digits) = match formatted.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", formatted.as_str()),
+        };
+        let (integer, fraction) = match digits.split_once('.') {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (digits, None),
+        };
+
+        let group = self.t("number.group");
+        let mut number = sign.to_string();
+        for (index, digit) in integer.chars().enumerate() {
+            if index > 0 && (integer.len() - index) % 3 == 0 {
+                number.push_str(group);
+            }
+            number.push(digit);
+        }
+        if let Some(fraction) = fraction {
+            number.push_str(self.t("number.decimal"));
+            number.push_str(fraction);
+        }
+        number
+    }
+
+    /// A rough length of time in its largest unit, e.g. `5 min`
+    pub fn duration(&self, duration: std::time::Duration) -> String {
+        let seconds = duration.as_secs();
+        let (key, count) = match seconds {
+            0..60 => ("duration.seconds", seconds),
+            60..3600 => ("duration.minutes", seconds / 60),
+            _ => ("duration.hours", seconds / 3600),
+        };
+        self.t_with(key, &[("count", &count.to_string())])
+    }
+
+    /// A date with the day of the week and the month spelled out, e.g.
+    /// `Monday, October 19`
+    pub fn long_date(&self, date: &impl Datelike) -> String {
+        let weekday_key = format!("date.weekdays.{}", date.weekday().num_days_from_monday());
+        let month_key = format!("date.months.{}", date.month0());
+        self.t_with(
+            "date.long",
+            &[
+                ("weekday", self.t(&weekday_key)),
+                ("month", self.t(&month_key)),
+                ("day", &date.day().to_string()),
+            ],
+        )
+    }
+
+    /// A time of day in hours and minutes, e.g. `18:30`
+    pub fn time(&self, time: &impl Timelike) -> String {
+        self.t_with(
+            "date.time",
+            &[
+                ("hour", &format!("{:02}", time.hour())),
+                ("minute", &format!("{:02}", time.minute())),
+            ],
+        )
+    }
+}
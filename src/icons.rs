@@ -0,0 +1,259 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    Extension,
+    extract::Path,
+    http::{HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use maud::{Markup, html};
+
+use crate::{
+    AppState,
+    auth::Identity,
+    config::{DEFAULT_ACCENT, Dashboard, Group, Service, Visibility},
+    error::VestaError,
+    services::icon_service::{icon_hash, icon_source, is_remote_icon},
+    widgets::quick_links_widget::QuickLinksWidget,
+};
+
+const ICON_CACHE_CONTROL: &str = "public, max-age=86400";
+/// Letter avatars stand in for icons that failed to load, so they are retried soon
+const AVATAR_CACHE_CONTROL: &str = "public, max-age=300";
+/// Icons may be SVG from a third party; opened directly they must not run scripts
+const ICON_CSP: &str = "default-src 'none'; style-src 'unsafe-inline'; sandbox";
+
+/// Serve `/icons/{hash}`, a cached copy of a remote icon from the config, or a
+/// letter avatar when it cannot be fetched. The branding icons are public, since
+/// the login page shows them; service icons need permission to view the service.
+pub async fn icon_handler(
+    Extension(state): Extension<Arc<AppState>>,
+    identity: Option<Extension<Identity>>,
+    Path(hash): Path<String>,
+) -> Result<Response, VestaError> {
+    // Only icons from the config are fetched, so this is not an open proxy
+    let icons = state.get_config_manager().icon_index();
+    let identity = identity.map(|Extension(identity)| identity);
+    let Some((source, title)) = icons.find(&hash, identity.as_ref()) else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+
+    let mut response = match state.get_icon_service().get_icon(source).await {
+        Ok(icon) => (
+            [
+                (header::CONTENT_TYPE, icon.content_type),
+                (header::CACHE_CONTROL, ICON_CACHE_CONTROL),
+            ],
+            icon.data,
+        )
+            .into_response(),
+        Err(_) => (
+            [
+                (header::CONTENT_TYPE, "image/svg+xml"),
+                (header::CACHE_CONTROL, AVATAR_CACHE_CONTROL),
+            ],
+            letter_avatar(title, &accent(&state)?).into_string(),
+        )
+            .into_response(),
+    };
+
+    let headers = response.headers_mut();
+    headers.insert(
+        header::CONTENT_SECURITY_POLICY,
+        HeaderValue::from_static(ICON_CSP),
+    );
+    headers.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+    Ok(response)
+}
+
+fn accent(state: &AppState) -> Result<String, VestaError> {
+    let config = state.get_config_manager().read_config()?;
+    Ok(config.theme.accent().unwrap_or(DEFAULT_ACCENT).to_string())
+}
+
+/// A place in the config that uses a remote icon
+struct IconUse {
+    source: String,
+    /// Title for the letter avatar shown when the icon can't be fetched
+    title: String,
+    /// Visibility of the service and of each group around it; `None` for the
+    /// favicon and logo, which the login page shows to everyone
+    visibility: Option<Vec<Visibility>>,
+}
+
+/// The remote icons of a config by the hash in their `/icons/{hash}` URL, built
+/// when the config is loaded so that requests don't search the whole config
+#[derive(Default)]
+pub struct IconIndex {
+    icons: HashMap<String, Vec<IconUse>>,
+}
+
+impl IconIndex {
+    pub fn new(config: &Dashboard) -> Self {
+        let mut index = Self::default();
+        let settings = &config.settings;
+        for icon in [&settings.favicon, &settings.logo].into_iter().flatten() {
+            index.insert(icon.clone(), settings.title.clone(), None);
+        }
+        for group in config.groups.values() {
+            index.add_group(group, &mut Vec::new());
+        }
+        index
+    }
+
+    fn add_group(&mut self, group: &Group, visibility: &mut Vec<Visibility>) {
+        visibility.push(group.visibility.clone());
+        for service in &group.services {
+            let mut service_visibility = visibility.clone();
+            service_visibility.push(service.visibility.clone());
+            for (source, title) in service_icons(service) {
+                self.insert(source, title, Some(service_visibility.clone()));
+            }
+        }
+        for child in group.groups.values() {
+            self.add_group(child, visibility);
+        }
+        visibility.pop();
+    }
+
+    fn insert(&mut self, source: String, title: String, visibility: Option<Vec<Visibility>>) {
+        if is_remote_icon(&source) {
+            self.icons
+                .entry(icon_hash(&source))
+                .or_default()
+                .push(IconUse {
+                    source,
+                    title,
+                    visibility,
+                });
+        }
+    }
+
+    /// The source and title of the icon with `hash`, if it is used somewhere
+    /// `identity` may see
+    pub fn find(&self, hash: &str, identity: Option<&Identity>) -> Option<(&str, &str)> {
+        let can_see = |icon: &&IconUse| match (&icon.visibility, identity) {
+            (None, _) => true,
+            (Some(visibility), Some(identity)) => {
+                identity.can_view() && visibility.iter().all(|v| v.is_visible_to(identity))
+            }
+            (Some(_), None) => false,
+        };
+        self.icons
+            .get(hash)?
+            .iter()
+            .find(can_see)
+            .map(|icon| (icon.source.as_str(), icon.title.as_str()))
+    }
+}
+
+/// Icon sources of a service and its quick links, each with the title it belongs to
+fn service_icons(service: &Service) -> Vec<(String, String)> {
+    let mut icons = Vec::new();
+    if let Some(img_src) = &service.img_src {
+        let source = icon_source(img_src, service.href.as_deref());
+        icons.push((source, service.title.clone()));
+    }
+
+    let quick_links = service
+        .widget
+        .as_ref()
+        .filter(|widget| widget.name == "QuickLinks")
+        .and_then(|widget| widget.config.as_ref())
+        .and_then(|config| QuickLinksWidget::parse_config(config).ok())
+        .unwrap_or_default();
+    for link in quick_links {
+        if let Some(icon) = link.icon {
+            icons.push((icon_source(&icon, Some(&link.url)), link.title));
+        }
+    }
+
+    icons
+}
+
+/// A square with the first letter of `title`, in the style of the dashboard. It is
+/// loaded as an image, so it can't use the page's CSS variables for `accent`.
+fn letter_avatar(title: &str, accent: &str) -> Markup {
+    let letter = title
+        .chars()
+        .next()
+        .map(|c| c.to_uppercase().to_string())
+        .unwrap_or_else(|| "?".to_string());
+
+    html! {
+        svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 64 64" width="64" height="64" {
+            rect x="1" y="1" width="62" height="62" rx="10"
+                fill=(accent) fill-opacity="0.2" stroke=(accent) stroke-opacity="0.3" stroke-width="2" {}
+            text x="32" y="32" dy="0.35em" text-anchor="middle"
+                font-family="ui-monospace, monospace" font-size="32" font-weight="700" fill=(accent) {
+                (letter)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Dashboard {
+        toml::from_str(
+            r#"
+            [settings]
+            title = "Home"
+            favicon = "di:home"
+
+            [media]
+            name = "Media"
+            columns = 4
+            services = [
+                { title = "Jellyfin", img_src = "di:jellyfin" },
+                { title = "Links", widget = { name = "QuickLinks", config = { link_0_title = "Wiki", link_0_url = "https://wiki.example.com", link_0_icon = "si:wikipedia" } } },
+            ]
+
+            [admin]
+            name = "Admin"
+            columns = 4
+            allowed_roles = ["admins"]
+            services = [{ title = "Router", img_src = "https://router.example.com/icon.png" }]
+            "#,
+        )
+        .unwrap()
+    }
+
+    fn viewer() -> Identity {
+        Identity::new(&config().auth, "kid", Vec::new())
+    }
+
+    #[test]
+    fn branding_icons_are_public() {
+        let index = IconIndex::new(&config());
+        assert_eq!(
+            index.find(&icon_hash("di:home"), None),
+            Some(("di:home", "Home"))
+        );
+    }
+
+    #[test]
+    fn service_and_quick_link_icons_need_a_viewer() {
+        let index = IconIndex::new(&config());
+        for (source, title) in [("di:jellyfin", "Jellyfin"), ("si:wikipedia", "Wiki")] {
+            assert_eq!(index.find(&icon_hash(source), None), None);
+            assert_eq!(
+                index.find(&icon_hash(source), Some(&viewer())),
+                Some((source, title))
+            );
+        }
+    }
+
+    #[test]
+    fn icons_of_hidden_groups_are_not_found() {
+        let index = IconIndex::new(&config());
+        let hash = icon_hash("https://router.example.com/icon.png");
+        assert_eq!(index.find(&hash, Some(&viewer())), None);
+        assert!(index.find(&hash, Some(&Identity::anonymous())).is_some());
+    }
+}
@@ -0,0 +1,116 @@
+use std::time::Duration;
+
+use axum::{body::Body, extract::Request, response::Response};
+use tracing::{Span, field};
+use tracing_subscriber::{EnvFilter, Layer, fmt, layer::SubscriberExt, util::SubscriberInitExt};
+
+use crate::config::{LogFormat, LoggingConfig};
+
+/// Keeps trace exporters running; flushes them when dropped
+pub struct LogGuard {
+    #[cfg(feature = "otel")]
+    tracer_provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+impl Drop for LogGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        if let Some(provider) = self.tracer_provider.take()
+            && let Err(e) = provider.shutdown()
+        {
+            eprintln!("Error flushing traces: {}", e);
+        }
+    }
+}
+
+/// Install the global subscriber. `RUST_LOG` overrides the configured level.
+pub fn init(config: &LoggingConfig) -> LogGuard {
+    let invalid_level = EnvFilter::try_new(&config.level).err();
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(&config.level))
+        .unwrap_or_else(|_| EnvFilter::new("info"));
+
+    let output = match config.format {
+        LogFormat::Text => fmt::layer().boxed(),
+        LogFormat::Json => fmt::layer()
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .boxed(),
+    };
+    let registry = tracing_subscriber::registry().with(filter).with(output);
+
+    #[cfg(feature = "otel")]
+    let guard = {
+        let (otel, tracer_provider) = match config.otlp_endpoint.as_deref().map(otlp_provider) {
+            Some(Ok(provider)) => {
+                use opentelemetry::trace::TracerProvider as _;
+                let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer("vesta"));
+                (Some(layer), Some(provider))
+            }
+            Some(Err(e)) => {
+                eprintln!("Error setting up OTLP export: {}", e);
+                (None, None)
+            }
+            None => (None, None),
+        };
+        registry.with(otel).init();
+        LogGuard { tracer_provider }
+    };
+
+    #[cfg(not(feature = "otel"))]
+    let guard = {
+        registry.init();
+        if config.otlp_endpoint.is_some() {
+            tracing::warn!("otlp_endpoint is set, but Vesta was built without the otel feature");
+        }
+        LogGuard {}
+    };
+
+    if let Some(e) = invalid_level {
+        tracing::warn!(level = %config.level, error = %e, "Invalid log level, using info");
+    }
+    guard
+}
+
+#[cfg(feature = "otel")]
+fn otlp_provider(
+    endpoint: &str,
+) -> Result<opentelemetry_sdk::trace::SdkTracerProvider, opentelemetry_otlp::ExporterBuildError> {
+    use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::{Resource, trace::SdkTracerProvider};
+
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()?;
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name("vesta").build())
+        .build())
+}
+
+/// Span for an incoming request. Only the path is recorded, since query strings
+/// can carry secrets such as OpenID Connect authorization codes.
+pub fn request_span(request: &Request<Body>) -> Span {
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        path = %request.uri().path(),
+        status = field::Empty,
+        latency_ms = field::Empty,
+    )
+}
+
+pub fn log_response(response: &Response, latency: Duration, span: &Span) {
+    let status = response.status().as_u16();
+    let latency_ms = latency.as_millis() as u64;
+    span.record("status", status);
+    span.record("latency_ms", latency_ms);
+    if response.status().is_server_error() {
+        tracing::warn!(status, latency_ms, "Request failed");
+    } else {
+        tracing::info!(status, latency_ms, "Request finished");
+    }
+}
@@ -5,21 +5,32 @@ use std::time::Duration;
 use axum::{
     Router,
     extract::{Extension, Path, Query},
-    response::IntoResponse,
-    routing::{get, post},
+    middleware,
+    response::{IntoResponse, Redirect},
+    routing::{delete, get, post},
 };
 use reqwest::Client;
 use tower_http::compression::CompressionLayer;
-use tower_http::services::ServeDir;
+use tower_http::trace::TraceLayer;
 
-use config::Dashboard;
+use auth::Identity;
+use auth::oidc::OidcClient;
+use auth::password::CredentialCache;
+use auth::session::SessionStore;
+use auth::tokens::TokenStore;
+use config::{DEFAULT_CONFIG_PATH, Dashboard, icons_path, load_config, tokens_path};
 use config_manager::ConfigManager;
 use error::{VestaError, VestaResult};
 use http_client::create_default_client;
 use ping::ping_handler;
+use render::RenderContext;
+use server::listen::InheritedSockets;
+use services::action_service::ActionService;
+use services::icon_service::IconService;
 use services::ping_service::PingService;
 use services::system_stats_service::SystemStatsService;
 use services::weather_service::WeatherService;
+use supervisor::TaskSupervisor;
 use templates::dashboard;
 use widget_system::WidgetRegistry;
 use widgets::clock_widget::ClockWidget;
@@ -29,13 +40,25 @@ use widgets::system_stats_widget::SystemStatsWidget;
 use widgets::weather_widget::WeatherWidget;
 
 mod api;
+mod assets;
+mod auth;
+mod cli;
 mod config;
 mod config_manager;
+mod csp;
 mod error;
 mod http_client;
+mod i18n;
+mod icons;
+mod logging;
 mod ping;
+mod pwa;
+mod render;
 mod response;
+mod search;
+mod server;
 mod services;
+mod supervisor;
 mod templates;
 mod widget_system;
 mod widgets;
@@ -47,10 +70,18 @@ pub struct AppState {
     system_stats_service: Arc<SystemStatsService>,
     ping_service: Arc<PingService>,
     weather_service: Arc<WeatherService>,
+    icon_service: Arc<IconService>,
+    action_service: Arc<ActionService>,
+    session_store: Arc<SessionStore>,
+    credential_cache: Arc<CredentialCache>,
+    oidc_client: Arc<OidcClient>,
+    token_store: Arc<TokenStore>,
+    supervisor: TaskSupervisor,
 }
 
 impl AppState {
     const SYSTEM_STATS_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+    const TOKEN_SYNC_INTERVAL: Duration = Duration::from_secs(5);
 
     pub fn new(config_path: &str) -> VestaResult<Arc<Self>> {
         let widget_registry = Arc::new(
@@ -65,9 +96,36 @@ impl AppState {
         let config_manager = Arc::new(ConfigManager::new(config_path, widget_registry.clone())?);
         let http_client = create_default_client()?;
 
-        let system_stats_service = SystemStatsService::new(Self::SYSTEM_STATS_REFRESH_INTERVAL);
+        let supervisor = TaskSupervisor::new();
+
+        let system_stats_service = SystemStatsService::new();
+        let stats = system_stats_service.clone();
+        supervisor.spawn("system stats refresh", move || {
+            let stats = stats.clone();
+            async move {
+                stats
+                    .run_background_refresh(Self::SYSTEM_STATS_REFRESH_INTERVAL)
+                    .await;
+                Ok(())
+            }
+        });
+
         let ping_service = PingService::new(http_client.clone());
         let weather_service = WeatherService::new(http_client.clone());
+        let icon_service = IconService::new(http_client.clone(), icons_path(config_path));
+        let action_service = ActionService::new(http_client.clone());
+        let session_store = SessionStore::new();
+        let credential_cache = CredentialCache::new();
+        let oidc_client = OidcClient::new(http_client.clone());
+        let token_store = TokenStore::open(tokens_path(config_path))?;
+        let tokens = token_store.clone();
+        supervisor.spawn("token sync", move || {
+            let tokens = tokens.clone();
+            async move {
+                tokens.run_sync(Self::TOKEN_SYNC_INTERVAL).await;
+                Ok(())
+            }
+        });
 
         Ok(Arc::new(Self {
             config_manager,
@@ -76,9 +134,24 @@ impl AppState {
             system_stats_service,
             ping_service,
             weather_service,
+            icon_service,
+            action_service,
+            session_store,
+            credential_cache,
+            oidc_client,
+            token_store,
+            supervisor,
         }))
     }
 
+    /// Stop background tasks and persist state that has not been written yet
+    pub async fn shutdown(&self) {
+        self.supervisor.shutdown().await;
+        if let Err(e) = self.token_store.sync() {
+            tracing::error!(error = %e, "Error saving API tokens");
+        }
+    }
+
     pub fn reload_config(&self) -> VestaResult<()> {
         self.config_manager.reload_config()
     }
@@ -110,48 +183,185 @@ impl AppState {
     pub fn get_weather_service(&self) -> &WeatherService {
         &self.weather_service
     }
+
+    pub fn get_icon_service(&self) -> &IconService {
+        &self.icon_service
+    }
+
+    pub fn get_action_service(&self) -> &ActionService {
+        &self.action_service
+    }
+
+    pub fn get_session_store(&self) -> &SessionStore {
+        &self.session_store
+    }
+
+    pub fn get_credential_cache(&self) -> &CredentialCache {
+        &self.credential_cache
+    }
+
+    pub fn get_oidc_client(&self) -> &OidcClient {
+        &self.oidc_client
+    }
+
+    pub fn get_token_store(&self) -> &TokenStore {
+        &self.token_store
+    }
+
+    pub fn get_supervisor(&self) -> &TaskSupervisor {
+        &self.supervisor
+    }
 }
 
 async fn widget_handler(
     Path(widget_name): Path<String>,
     Query(query): Query<widget_system::WidgetQuery>,
     Extension(state): Extension<Arc<AppState>>,
+    Extension(identity): Extension<Identity>,
+    Extension(ctx): Extension<RenderContext>,
 ) -> Result<impl IntoResponse, VestaError> {
+    // Hidden services must not be reachable through their widgets either
+    state
+        .get_config_manager()
+        .get_visible_service(&query.group, &query.title, &identity)?
+        .ok_or_else(|| VestaError::ServiceNotFound {
+            group: query.group.clone(),
+            title: query.title.clone(),
+        })?;
+
     let state_clone = Arc::clone(&state);
     state
         .widget_registry
-        .handle_widget_request(&widget_name, state_clone, query)
+        .handle_widget_request(&widget_name, state_clone, &ctx, &identity, query)
         .await
 }
 
-#[tokio::main]
-async fn main() {
-    let config_path = "./config/vesta.toml";
-    let state = match AppState::new(config_path) {
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(code) = cli::run(&args) {
+        exit(code);
+    }
+
+    // Before the runtime starts its threads, as this changes the environment
+    let inherited = InheritedSockets::take();
+
+    let runtime = match tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("Failed to start the async runtime: {}", e);
+            exit(1);
+        }
+    };
+    runtime.block_on(run(inherited));
+}
+
+async fn run(inherited: std::io::Result<InheritedSockets>) {
+    // Logging is set up before anything else, so errors loading the config are
+    // reported the same way; those fall back to the default logging settings
+    let logging_config = load_config(DEFAULT_CONFIG_PATH)
+        .map(|config| config.logging)
+        .unwrap_or_default();
+    let log_guard = logging::init(&logging_config);
+
+    let inherited = match inherited {
+        Ok(inherited) => inherited,
+        Err(e) => {
+            tracing::error!(error = %e, "Error taking over sockets passed by systemd");
+            exit(1);
+        }
+    };
+
+    let state = match AppState::new(DEFAULT_CONFIG_PATH) {
         Ok(state) => state,
         Err(e) => {
-            eprintln!("Error when loading config: {}", e);
+            tracing::error!(error = %e, "Error when loading config");
             exit(1);
         }
     };
 
-    let app = Router::new()
+    let server_config = match state.get_config() {
+        Ok(config) => config.server,
+        Err(e) => {
+            tracing::error!(error = %e, "Error when loading config");
+            exit(1);
+        }
+    };
+
+    let admin_routes = Router::new()
+        .route("/api/widget", get(api::get_widget))
+        .route("/api/config/validate", get(api::validate_config))
+        .route("/api/tokens", get(api::list_tokens))
+        .route("/api/tokens/{id}", delete(api::revoke_token))
+        .route("/api/service/action", post(api::run_service_action))
+        .route_layer(middleware::from_fn(auth::require_admin))
+        .route_layer(middleware::from_fn(auth::require_same_origin));
+
+    let reload_routes = Router::new()
+        .route("/api/config/reload", post(api::reload_config))
+        .route_layer(middleware::from_fn(auth::require_reload))
+        .route_layer(middleware::from_fn(auth::require_same_origin));
+
+    let viewer_routes = Router::new()
         .route("/api/widgets/{widget_name}", get(widget_handler))
         .route("/api/ping", get(ping_handler))
-        .route("/api/health", get(api::health))
         .route("/api/services", get(api::list_services))
         .route("/api/service", get(api::get_service))
-        .route("/api/widget", get(api::get_widget))
-        .route("/api/config/validate", get(api::validate_config))
-        .route("/api/config/reload", post(api::reload_config))
+        .route("/api/search", get(api::search))
+        .route("/api/auth/me", get(api::whoami))
         .route("/", get(dashboard))
-        .nest_service("/static", ServeDir::new("static"))
+        .route_layer(middleware::from_fn(auth::require_viewer));
+
+    let app = Router::new()
+        .merge(admin_routes)
+        .merge(reload_routes)
+        .merge(viewer_routes)
+        .route("/api/health", get(api::health))
+        .route(
+            "/login",
+            get(auth::login::login_form).post(auth::login::login),
+        )
+        .route("/logout", post(auth::login::logout))
+        .route("/auth/oidc/login", get(auth::login::oidc_login))
+        .route("/auth/oidc/callback", get(auth::login::oidc_callback))
+        .route("/static/{*path}", get(assets::serve_asset))
+        .route("/custom.css", get(assets::serve_custom_css))
+        .route("/custom.js", get(assets::serve_custom_js))
+        .route("/manifest.webmanifest", get(pwa::manifest))
+        .route("/sw.js", get(pwa::service_worker))
+        .route("/icons/{hash}", get(icons::icon_handler))
+        .layer(middleware::from_fn(auth::authenticate))
+        .layer(middleware::from_fn(render::render_context))
+        .layer(middleware::from_fn(csp::content_security_policy))
         .layer(CompressionLayer::new())
-        .layer(Extension(state));
+        .layer(Extension(state.clone()));
 
-    let address = "0.0.0.0:3000";
+    let app = if server_config.base_path.is_empty() {
+        app
+    } else {
+        let base_path = server_config.base_path.clone();
+        Router::new()
+            .route(
+                &format!("{}/", base_path),
+                get(move || async move { Redirect::permanent(&base_path) }),
+            )
+            .nest(&server_config.base_path, app)
+    };
+    let app = app.layer(
+        TraceLayer::new_for_http()
+            .make_span_with(logging::request_span)
+            .on_response(logging::log_response)
+            .on_failure(()),
+    );
 
-    println!("Listening on http://{address}");
-    let listener = tokio::net::TcpListener::bind(address).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    state.get_supervisor().spawn_signal_handler();
+    let result = server::serve(app, &server_config, state.get_supervisor(), inherited).await;
+    state.shutdown().await;
+    if let Err(e) = result {
+        tracing::error!(error = %e, "Server error");
+        drop(log_guard);
+        exit(1);
+    }
 }
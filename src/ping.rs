@@ -4,7 +4,7 @@ use axum::{Extension, extract::Query, response::IntoResponse};
 use maud::{Markup, html};
 use serde::Deserialize;
 
-use crate::{AppState, error::VestaError};
+use crate::{AppState, auth::Identity, error::VestaError, render::RenderContext};
 
 #[derive(Deserialize)]
 pub struct QueryParams {
@@ -14,26 +14,26 @@ pub struct QueryParams {
 
 pub async fn ping_handler(
     Extension(state): Extension<Arc<AppState>>,
+    Extension(identity): Extension<Identity>,
     Query(params): Query<QueryParams>,
 ) -> Result<impl IntoResponse, VestaError> {
     let config = state.get_config()?;
     let service_info = config
-        .get_service(&params.group, &params.title)
+        .get_visible_service(&params.group, &params.title, &identity)
         .ok_or_else(|| VestaError::ServiceNotFound {
             group: params.group.clone(),
             title: params.title.clone(),
         })?;
 
-    let ping_config = service_info
-        .ping
-        .as_ref()
+    let ping_url = service_info
+        .ping_url()
         .ok_or_else(|| VestaError::MissingWidgetConfig {
             service: service_info.title.clone(),
         })?;
 
     let ping_service = state.get_ping_service();
     let is_up = ping_service
-        .check_service(&params.group, &params.title, ping_config)
+        .check_service(&params.group, &params.title, ping_url)
         .await
         .unwrap_or(false);
 
@@ -48,11 +48,11 @@ fn render_ping_indicator(is_up: bool) -> Markup {
     }
 }
 
-pub fn render_service_indicator(group_id: &str, title: &str) -> Markup {
+pub fn render_service_indicator(ctx: &RenderContext, group_id: &str, title: &str) -> Markup {
     html! {
         div
-            class="w-2 h-2 bg-slate-900"
-            hx-get=(format!("/api/ping?group={}&title={}", group_id, title))
+            class="w-2 h-2 bg-surface-900"
+            hx-get=(ctx.ping_url(group_id, title))
             hx-trigger="load"
             hx-swap="outerHTML" { }
     }
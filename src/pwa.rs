@@ -0,0 +1,108 @@
+use axum::{
+    Extension,
+    http::{HeaderValue, header},
+    response::{IntoResponse, Response},
+};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    assets::{self, short_hash},
+    config::{DEFAULT_ACCENT, ThemeMode},
+    render::RenderContext,
+};
+
+const MANIFEST_CONTENT_TYPE: &str = "application/manifest+json";
+const DARK_BACKGROUND: &str = "#09090b";
+const LIGHT_BACKGROUND: &str = "#fafafa";
+/// Size of `static/favicon.png`
+const FAVICON_SIZE: &str = "512x512";
+
+/// Assets the service worker keeps for opening the dashboard offline
+const SHELL_ASSETS: [&str; 4] = ["style.css", "htmx.min.js", "app.js", "favicon.png"];
+
+/// Serve `/manifest.webmanifest`, so the dashboard can be installed to a home screen
+pub async fn manifest(Extension(ctx): Extension<RenderContext>) -> Response {
+    let settings = &ctx.settings;
+    let mut icons = vec![json!({
+        "src": ctx.asset_url("favicon.png"),
+        "sizes": FAVICON_SIZE,
+        "type": "image/png"
+    })];
+    // Sizes of configured icons are unknown, so they are offered for any size
+    icons.extend(
+        [&settings.logo, &settings.favicon]
+            .into_iter()
+            .flatten()
+            .map(|icon| json!({ "src": ctx.icon_url(icon), "sizes": "any" })),
+    );
+
+    let background = match ctx.theme.mode {
+        ThemeMode::Light => LIGHT_BACKGROUND,
+        ThemeMode::Dark | ThemeMode::Auto => DARK_BACKGROUND,
+    };
+    let manifest = json!({
+        "name": settings.title,
+        "short_name": settings.wordmark,
+        "start_url": ctx.url("/"),
+        "scope": ctx.url("/"),
+        "display": "standalone",
+        "background_color": background,
+        "theme_color": theme_color(&ctx),
+        "icons": icons
+    });
+
+    (
+        [
+            (header::CONTENT_TYPE, MANIFEST_CONTENT_TYPE),
+            (header::CACHE_CONTROL, "no-cache"),
+        ],
+        manifest.to_string(),
+    )
+        .into_response()
+}
+
+/// Serve `/sw.js`, the service worker that caches the shell and the last dashboard.
+/// It lives next to the dashboard rather than in `/static`, since a service worker
+/// only controls pages below its own URL.
+pub async fn service_worker(Extension(ctx): Extension<RenderContext>) -> Response {
+    let Some(script) = assets::embedded("sw.js") else {
+        return axum::http::StatusCode::NOT_FOUND.into_response();
+    };
+
+    let shell_urls: Vec<String> = SHELL_ASSETS
+        .iter()
+        .map(|asset| ctx.asset_url(asset))
+        .collect();
+    // Versioned URLs change with the assets, which changes the worker, which
+    // makes browsers install it again
+    let version = short_hash(&Sha256::digest(shell_urls.join("\n")));
+    let prelude = format!(
+        "const SHELL_URLS = {};\nconst DASHBOARD_URL = {};\nconst LOGOUT_URL = {};\nconst CACHE_VERSION = {};\n\n",
+        json!(shell_urls),
+        json!(ctx.url("/")),
+        json!(ctx.url("/logout")),
+        json!(version)
+    );
+
+    let mut response = (
+        [
+            (header::CONTENT_TYPE, "text/javascript"),
+            (header::CACHE_CONTROL, "no-cache"),
+        ],
+        prelude + &String::from_utf8_lossy(&script),
+    )
+        .into_response();
+    // The dashboard is at e.g. `/dash`, outside the default scope of `/dash/sw.js`
+    if let Ok(scope) = HeaderValue::from_str(&ctx.url("/")) {
+        response
+            .headers_mut()
+            .insert("Service-Worker-Allowed", scope);
+    }
+    response
+}
+
+/// Colour of the browser UI around the installed app
+pub fn theme_color(ctx: &RenderContext) -> &str {
+    ctx.theme.accent().unwrap_or(DEFAULT_ACCENT)
+}
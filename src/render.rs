@@ -0,0 +1,187 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension,
+    extract::{ConnectInfo, Request},
+    http::header,
+    middleware::Next,
+    response::Response,
+};
+
+use crate::{
+    AppState,
+    assets::versioned_path,
+    config::{Service, SettingsConfig, ThemeConfig},
+    i18n::Locale,
+    server::PeerAddr,
+    services::icon_service::{icon_hash, is_remote_icon},
+};
+
+const FORWARDED_PREFIX_HEADER: &str = "X-Forwarded-Prefix";
+pub const CUSTOM_CSS: &str = "custom.css";
+pub const CUSTOM_JS: &str = "custom.js";
+
+/// Per-request information needed to render pages and build URLs
+#[derive(Debug, Clone, Default)]
+pub struct RenderContext {
+    /// Prefix Vesta is served under, without a trailing slash (empty at the root)
+    pub base_path: String,
+    pub theme: ThemeConfig,
+    pub settings: SettingsConfig,
+    /// Whether `custom.css` and `custom.js` exist next to the config file
+    pub custom_css: bool,
+    pub custom_js: bool,
+    /// The network the client is on, which picks between a service's hrefs
+    pub network: Option<String>,
+    pub locale: Locale,
+}
+
+impl RenderContext {
+    /// Absolute URL of a path within Vesta, e.g. `/static/app.js`
+    pub fn url(&self, path: &str) -> String {
+        // The dashboard itself lives at `/dash`, not `/dash/`
+        if path == "/" && !self.base_path.is_empty() {
+            return self.base_path.clone();
+        }
+        format!("{}{}", self.base_path, path)
+    }
+
+    /// URL of a file in `static/`, versioned with its content hash so it can be cached forever
+    pub fn asset_url(&self, path: &str) -> String {
+        self.url(&format!("/static/{}", versioned_path(path)))
+    }
+
+    /// URL to show an icon from the config with; remote icons go through the local cache
+    pub fn icon_url(&self, src: &str) -> String {
+        if is_remote_icon(src) {
+            self.url(&format!("/icons/{}", icon_hash(src)))
+        } else {
+            src.to_string()
+        }
+    }
+
+    /// The URL of a service for this client's network
+    pub fn service_href<'a>(&self, service: &'a Service) -> Option<&'a str> {
+        service.href_for(self.network.as_deref())
+    }
+
+    pub fn widget_url(&self, widget_name: &str, group_id: &str, title: &str) -> String {
+        self.url(&format!(
+            "/api/widgets/{}?group={}&title={}",
+            widget_name,
+            encode_query_value(group_id),
+            encode_query_value(title)
+        ))
+    }
+
+    pub fn action_url(&self, group_id: &str, title: &str, action: &str) -> String {
+        self.url(&format!(
+            "/api/service/action?group={}&title={}&action={}",
+            encode_query_value(group_id),
+            encode_query_value(title),
+            encode_query_value(action)
+        ))
+    }
+
+    pub fn ping_url(&self, group_id: &str, title: &str) -> String {
+        self.url(&format!(
+            "/api/ping?group={}&title={}",
+            encode_query_value(group_id),
+            encode_query_value(title)
+        ))
+    }
+}
+
+/// Work out the base path, theme, branding, client network and language of a
+/// request and store a [`RenderContext`] for it.
+///
+/// A trusted reverse proxy that strips a prefix announces it with
+/// `X-Forwarded-Prefix`, which is put in front of the configured `base_path`.
+pub async fn render_context(
+    Extension(state): Extension<Arc<AppState>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let config_manager = state.get_config_manager();
+    let ctx = match config_manager.read_config() {
+        Ok(config) => {
+            let peer = request
+                .extensions()
+                .get::<ConnectInfo<PeerAddr>>()
+                .map(|info| info.0);
+            let trusted = peer.is_some_and(|peer| peer.is_trusted_proxy(&config.server));
+
+            let forwarded = request
+                .headers()
+                .get(FORWARDED_PREFIX_HEADER)
+                .filter(|_| trusted)
+                .and_then(|value| value.to_str().ok())
+                .map(normalize_base_path)
+                .unwrap_or_default();
+
+            let named_network = config
+                .server
+                .network_header
+                .as_deref()
+                .filter(|_| trusted)
+                .and_then(|header| request.headers().get(header))
+                .and_then(|value| value.to_str().ok());
+            let client = peer.and_then(|peer| peer.client_ip(request.headers(), &config.server));
+            let network = config
+                .network_for(named_network, client)
+                .map(str::to_string);
+
+            // The configured locale wins over the browser's languages
+            let locale = config
+                .settings
+                .locale
+                .as_deref()
+                .and_then(Locale::find)
+                .or_else(|| {
+                    request
+                        .headers()
+                        .get(header::ACCEPT_LANGUAGE)
+                        .and_then(|value| value.to_str().ok())
+                        .and_then(Locale::negotiate)
+                })
+                .unwrap_or_default();
+
+            let custom_assets = config_manager.custom_assets();
+            RenderContext {
+                base_path: format!("{}{}", forwarded, config.server.base_path),
+                theme: config.theme.clone(),
+                settings: config.settings.clone(),
+                custom_css: custom_assets.css,
+                custom_js: custom_assets.js,
+                network,
+                locale,
+            }
+        }
+        Err(_) => RenderContext::default(),
+    };
+
+    request.extensions_mut().insert(ctx);
+    next.run(request).await
+}
+
+/// Turn `dash`, `/dash/` or `/dash` into `/dash`, and `/` into an empty string
+pub fn normalize_base_path(path: &str) -> String {
+    let trimmed = path.trim().trim_matches('/');
+    if trimmed.is_empty() {
+        String::new()
+    } else {
+        format!("/{}", trimmed)
+    }
+}
+
+pub fn encode_query_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
@@ -93,6 +93,16 @@ impl From<crate::error::VestaError> for JSendResponse<Value> {
                 code: Some(502),
                 data: Some(json!({ "details": http_error.to_string() })),
             },
+            crate::error::VestaError::Unauthorized => JSendResponse::Error {
+                message: "Authentication required".to_string(),
+                code: Some(401),
+                data: None,
+            },
+            crate::error::VestaError::Forbidden(message) => JSendResponse::Error {
+                message,
+                code: Some(403),
+                data: None,
+            },
             crate::error::VestaError::ApiError { status, message } => JSendResponse::Error {
                 message: format!("API error: {}", message),
                 code: Some(status.as_u16() as u32),
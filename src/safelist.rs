@@ -1,60 +0,0 @@
-// sm:col-span-2
-// sm:col-span-3
-// sm:col-span-4
-// sm:col-span-5
-// sm:col-span-6
-// sm:col-span-7
-// sm:col-span-8
-// sm:col-span-9
-// sm:col-span-10
-// sm:col-span-11
-// sm:col-span-12
-
-// col-span-2
-// col-span-3
-// col-span-4
-// col-span-5
-// col-span-6
-// col-span-7
-// col-span-8
-// col-span-9
-// col-span-10
-// col-span-11
-// col-span-12
-
-// row-span-2
-// row-span-3
-// row-span-4
-// row-span-5
-// row-span-6
-// row-span-7
-// row-span-8
-// row-span-9
-// row-span-10
-// row-span-11
-// row-span-12
-
-// sm:row-span-2
-// sm:row-span-3
-// sm:row-span-4
-// sm:row-span-5
-// sm:row-span-6
-// sm:row-span-7
-// sm:row-span-8
-// sm:row-span-9
-// sm:row-span-10
-// sm:row-span-11
-// sm:row-span-12
-
-// sm:grid-cols-1
-// sm:grid-cols-2
-// sm:grid-cols-3
-// sm:grid-cols-4
-// sm:grid-cols-5
-// sm:grid-cols-6
-// sm:grid-cols-7
-// sm:grid-cols-8
-// sm:grid-cols-9
-// sm:grid-cols-10
-// sm:grid-cols-11
-// sm:grid-cols-12
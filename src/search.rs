@@ -0,0 +1,177 @@
+use serde::Serialize;
+
+use crate::{
+    config::{Dashboard, SearchProvider},
+    render::{RenderContext, encode_query_value},
+    services::icon_service::icon_source,
+    widgets::quick_links_widget::QuickLinksWidget,
+};
+
+/// Most results returned for services and links
+const MAX_RESULTS: usize = 8;
+const BANG_PREFIX: char = '!';
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchResultKind {
+    Service,
+    Link,
+    Web,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchResult {
+    pub kind: SearchResultKind,
+    pub title: String,
+    /// Where the result comes from: the description or group of a service, the
+    /// service of a link, or the search provider
+    pub subtitle: String,
+    pub url: String,
+    pub icon: Option<String>,
+    #[serde(skip)]
+    score: u32,
+}
+
+/// Services (by title, alias, tag or group) and QuickLinks matching `query`, best first, followed by a web search.
+/// A query starting with a provider's `!bang` only searches the web.
+pub fn search(ctx: &RenderContext, config: &Dashboard, query: &str) -> Vec<SearchResult> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let providers = config.search.providers();
+    if let Some((provider, terms)) = find_bang(&providers, query) {
+        return web_search(provider, terms).into_iter().collect();
+    }
+
+    let mut results = Vec::new();
+    for (_, group) in config.all_groups() {
+        for service in &group.services {
+            if let Some(href) = ctx.service_href(service) {
+                // Aliases count as much as the title, tags and the group name less
+                let names = std::iter::once(&service.title).chain(&service.aliases);
+                let labels = std::iter::once(&group.name).chain(&service.tags);
+                let score = names
+                    .filter_map(|name| match_score(query, name))
+                    .chain(
+                        labels.filter_map(|label| match_score(query, label).map(|score| score / 2)),
+                    )
+                    .max();
+                if let Some(score) = score {
+                    let icon = service.img_src.as_deref().map(|img_src| {
+                        ctx.icon_url(&icon_source(img_src, service.href.as_deref()))
+                    });
+                    results.push(SearchResult {
+                        kind: SearchResultKind::Service,
+                        title: service.title.clone(),
+                        subtitle: service
+                            .description
+                            .clone()
+                            .unwrap_or_else(|| group.name.clone()),
+                        url: href.to_string(),
+                        icon,
+                        score,
+                    });
+                }
+            }
+
+            let quick_links = service
+                .widget
+                .as_ref()
+                .filter(|widget| widget.name == "QuickLinks")
+                .and_then(|widget| widget.config.as_ref())
+                .and_then(|config| QuickLinksWidget::parse_config(config).ok())
+                .unwrap_or_default();
+            for link in quick_links {
+                if let Some(score) = match_score(query, &link.title) {
+                    let icon = link
+                        .icon
+                        .as_deref()
+                        .map(|icon| ctx.icon_url(&icon_source(icon, Some(&link.url))));
+                    results.push(SearchResult {
+                        kind: SearchResultKind::Link,
+                        title: link.title,
+                        subtitle: service.title.clone(),
+                        url: link.url,
+                        icon,
+                        score,
+                    });
+                }
+            }
+        }
+    }
+
+    // Stable, so equally good matches keep the order of the config
+    results.sort_by_key(|result| std::cmp::Reverse(result.score));
+    results.truncate(MAX_RESULTS);
+    results.extend(
+        providers
+            .first()
+            .and_then(|provider| web_search(provider, query)),
+    );
+    results
+}
+
+/// Split `!g rust` into the provider with bang `g` and `rust`
+fn find_bang<'a>(
+    providers: &'a [SearchProvider],
+    query: &'a str,
+) -> Option<(&'a SearchProvider, &'a str)> {
+    let rest = query.strip_prefix(BANG_PREFIX)?;
+    let (bang, terms) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let provider = providers
+        .iter()
+        .find(|provider| provider.bang.as_deref() == Some(bang))?;
+    Some((provider, terms.trim()))
+}
+
+fn web_search(provider: &SearchProvider, terms: &str) -> Option<SearchResult> {
+    if terms.is_empty() {
+        return None;
+    }
+    Some(SearchResult {
+        kind: SearchResultKind::Web,
+        title: terms.to_string(),
+        subtitle: provider.name.clone(),
+        url: provider.url.replace("{query}", &encode_query_value(terms)),
+        icon: None,
+        score: 0,
+    })
+}
+
+/// How well `query` matches `text`, or `None` if it does not. Exact matches rank
+/// above prefixes, prefixes above word starts, word starts above substrings, and
+/// those above the letters merely appearing in order.
+fn match_score(query: &str, text: &str) -> Option<u32> {
+    let query = query.to_lowercase();
+    let text = text.to_lowercase();
+
+    if text == query {
+        return Some(1000);
+    }
+    if text.starts_with(&query) {
+        return Some(800);
+    }
+    if let Some(index) = text.find(&query) {
+        let at_word_start = text[..index]
+            .chars()
+            .next_back()
+            .is_some_and(|c| !c.is_alphanumeric());
+        return Some(if at_word_start { 600 } else { 400 });
+    }
+
+    // Letters in order, e.g. `jf` in `jellyfin`; the fewer letters skipped, the better
+    let mut skipped = 0;
+    let mut text_chars = text.chars();
+    for wanted in query.chars() {
+        loop {
+            match text_chars.next() {
+                Some(c) if c == wanted => break,
+                Some(_) => skipped += 1,
+                None => return None,
+            }
+        }
+    }
+    Some(200u32.saturating_sub(skipped * 10).max(1))
+}
@@ -0,0 +1,187 @@
+use std::fs;
+use std::io;
+use std::net::SocketAddr;
+use std::os::fd::{FromRawFd, RawFd};
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+use socket2::{Domain, Socket, Type};
+use tokio::net::{TcpListener, UnixListener};
+
+use crate::config::ServerConfig;
+
+const UNIX_PREFIX: &str = "unix:";
+/// First file descriptor passed by systemd socket activation
+const LISTEN_FDS_START: RawFd = 3;
+const TCP_BACKLOG: i32 = 1024;
+
+/// A socket Vesta accepts HTTP connections on
+pub enum BoundListener {
+    Tcp(TcpListener),
+    /// `owned` when Vesta created the socket file, and so removes it on shutdown;
+    /// sockets passed by systemd belong to their `.socket` unit
+    Unix {
+        listener: UnixListener,
+        path: PathBuf,
+        owned: bool,
+    },
+}
+
+/// Sockets passed with the systemd socket activation protocol (`LISTEN_FDS`)
+pub struct InheritedSockets(Vec<Socket>);
+
+impl InheritedSockets {
+    /// Take the sockets systemd passed to this process and remove the variables
+    /// announcing them, so commands run by service actions neither see nor
+    /// inherit them.
+    ///
+    /// Changing the environment is only sound while no other thread may read it,
+    /// so this must be called from `main` before the runtime or logging start
+    /// any threads.
+    pub fn take() -> io::Result<Self> {
+        let for_us = std::env::var("LISTEN_PID")
+            .ok()
+            .and_then(|pid| pid.parse::<u32>().ok())
+            .is_some_and(|pid| pid == std::process::id());
+        let count = std::env::var("LISTEN_FDS")
+            .ok()
+            .and_then(|count| count.parse::<RawFd>().ok())
+            .filter(|_| for_us)
+            .unwrap_or(0);
+        // SAFETY: called before any other thread exists, see above
+        unsafe {
+            std::env::remove_var("LISTEN_PID");
+            std::env::remove_var("LISTEN_FDS");
+            std::env::remove_var("LISTEN_FDNAMES");
+        }
+
+        let sockets = (LISTEN_FDS_START..LISTEN_FDS_START + count)
+            .map(|fd| {
+                // SAFETY: systemd hands these descriptors to this process, which
+                // is their only owner, and they are taken exactly once
+                let socket = unsafe { Socket::from_raw_fd(fd) };
+                // systemd passes them without FD_CLOEXEC
+                socket.set_cloexec(true)?;
+                Ok(socket)
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Self(sockets))
+    }
+}
+
+/// Take over the sockets passed by systemd, or bind the configured addresses
+pub async fn bind_all(
+    config: &ServerConfig,
+    inherited: InheritedSockets,
+) -> io::Result<Vec<BoundListener>> {
+    if !inherited.0.is_empty() {
+        return inherited.0.into_iter().map(inherited_listener).collect();
+    }
+
+    let mut listeners = Vec::new();
+    for address in config.listen_addresses() {
+        let listener = match address.strip_prefix(UNIX_PREFIX) {
+            Some(path) => bind_unix(Path::new(path), config.socket_mode)?,
+            None => bind_tcp(address).await?,
+        };
+        listeners.push(listener);
+    }
+    Ok(listeners)
+}
+
+async fn bind_tcp(address: &str) -> io::Result<BoundListener> {
+    let addr = tokio::net::lookup_host(address)
+        .await
+        .map_err(|e| with_address(address, e))?
+        .next()
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} did not resolve to an address", address),
+            )
+        })?;
+    let listener = tcp_listener(addr).map_err(|e| with_address(address, e))?;
+    Ok(BoundListener::Tcp(listener))
+}
+
+fn tcp_listener(addr: SocketAddr) -> io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, None)?;
+    // Allow listening on both 0.0.0.0 and [::] with the same port
+    if addr.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(TCP_BACKLOG)?;
+    TcpListener::from_std(socket.into())
+}
+
+fn bind_unix(path: &Path, mode: Option<u32>) -> io::Result<BoundListener> {
+    // A socket left behind by a previous run would make bind fail
+    if let Ok(metadata) = fs::symlink_metadata(path)
+        && metadata.file_type().is_socket()
+    {
+        fs::remove_file(path)?;
+    }
+
+    let listener = match mode {
+        Some(mode) => bind_unix_with_mode(path, mode),
+        None => UnixListener::bind(path),
+    }
+    .map_err(|e| with_path(path, e))?;
+    Ok(BoundListener::Unix {
+        listener,
+        path: path.to_path_buf(),
+        owned: true,
+    })
+}
+
+/// Bind in a directory only Vesta can enter and move the socket into place once
+/// it has `mode`, so it is never reachable with the default permissions
+fn bind_unix_with_mode(path: &Path, mode: u32) -> io::Result<UnixListener> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let private_dir = path.with_file_name(format!(".{}.{}", file_name, std::process::id()));
+    fs::DirBuilder::new().mode(0o700).create(&private_dir)?;
+
+    let private_path = private_dir.join("socket");
+    let result = UnixListener::bind(&private_path).and_then(|listener| {
+        fs::set_permissions(&private_path, fs::Permissions::from_mode(mode))?;
+        fs::rename(&private_path, path)?;
+        Ok(listener)
+    });
+    let _ = fs::remove_file(&private_path);
+    let _ = fs::remove_dir(&private_dir);
+    result
+}
+
+/// A listener for a socket passed by systemd
+fn inherited_listener(socket: Socket) -> io::Result<BoundListener> {
+    socket.set_nonblocking(true)?;
+
+    let local_addr = socket.local_addr()?;
+    if local_addr.as_socket().is_some() {
+        let listener = std::net::TcpListener::from(socket);
+        return Ok(BoundListener::Tcp(TcpListener::from_std(listener)?));
+    }
+
+    // Not an IP socket, so it must be a Unix socket
+    let path = local_addr
+        .as_pathname()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    let listener = std::os::unix::net::UnixListener::from(socket);
+    Ok(BoundListener::Unix {
+        listener: UnixListener::from_std(listener)?,
+        path,
+        owned: false,
+    })
+}
+
+fn with_address(address: &str, e: io::Error) -> io::Error {
+    io::Error::new(e.kind(), format!("Failed to listen on {}: {}", address, e))
+}
+
+fn with_path(path: &Path, e: io::Error) -> io::Error {
+    io::Error::new(e.kind(), format!("Failed to listen on {:?}: {}", path, e))
+}
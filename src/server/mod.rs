@@ -0,0 +1,305 @@
+pub mod listen;
+pub mod tls;
+
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    Router,
+    extract::connect_info::Connected,
+    http::HeaderMap,
+    serve::{IncomingStream, Listener},
+};
+use tokio::net::{TcpListener, UnixListener};
+use tokio::task::JoinSet;
+
+use crate::{config::ServerConfig, supervisor::TaskSupervisor};
+use listen::{BoundListener, InheritedSockets};
+use tls::{ReloadableCert, TlsListener, redirect_router};
+
+/// How long in-flight requests get to finish after a shutdown signal
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+const FORWARDED_FOR_HEADER: &str = "X-Forwarded-For";
+
+/// Address of the client connected to Vesta
+#[derive(Debug, Clone, Copy)]
+pub enum PeerAddr {
+    Tcp(SocketAddr),
+    /// Connected over a Unix socket, which has no peer address
+    Unix,
+}
+
+impl PeerAddr {
+    /// Whether forwarding headers sent by this peer can be believed. Only local
+    /// processes allowed by the socket's permissions can connect over a Unix socket.
+    pub fn is_trusted_proxy(&self, config: &ServerConfig) -> bool {
+        match self {
+            Self::Tcp(addr) => config.is_trusted_proxy(addr.ip()),
+            Self::Unix => true,
+        }
+    }
+
+    /// Address of the client behind any trusted proxies: walking `X-Forwarded-For`
+    /// from the right, the first address that is not a trusted proxy itself.
+    /// `None` when a Unix socket peer does not say who it forwards for.
+    pub fn client_ip(&self, headers: &HeaderMap, config: &ServerConfig) -> Option<IpAddr> {
+        let mut client = match self {
+            Self::Tcp(addr) => Some(addr.ip().to_canonical()),
+            Self::Unix => None,
+        };
+        if !self.is_trusted_proxy(config) {
+            return client;
+        }
+
+        let forwarded: Vec<&str> = headers
+            .get_all(FORWARDED_FOR_HEADER)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .collect();
+        for entry in forwarded.into_iter().rev() {
+            let Some(addr) = parse_forwarded_addr(entry) else {
+                break;
+            };
+            client = Some(addr);
+            if !config.is_trusted_proxy(addr) {
+                break;
+            }
+        }
+        client
+    }
+}
+
+/// `192.0.2.1`, `2001:db8::1`, or either with a port, as some proxies send
+fn parse_forwarded_addr(entry: &str) -> Option<IpAddr> {
+    let entry = entry.trim();
+    entry
+        .parse::<IpAddr>()
+        .or_else(|_| entry.parse::<SocketAddr>().map(|addr| addr.ip()))
+        .ok()
+        .map(|addr| addr.to_canonical())
+}
+
+impl Connected<IncomingStream<'_, TcpListener>> for PeerAddr {
+    fn connect_info(stream: IncomingStream<'_, TcpListener>) -> Self {
+        Self::Tcp(*stream.remote_addr())
+    }
+}
+
+impl Connected<IncomingStream<'_, TlsListener>> for PeerAddr {
+    fn connect_info(stream: IncomingStream<'_, TlsListener>) -> Self {
+        Self::Tcp(*stream.remote_addr())
+    }
+}
+
+impl Connected<IncomingStream<'_, UnixListener>> for PeerAddr {
+    fn connect_info(_stream: IncomingStream<'_, UnixListener>) -> Self {
+        Self::Unix
+    }
+}
+
+/// Bind the configured listeners and serve the app until shutdown or until one of
+/// them fails. On shutdown, in-flight requests get [`DRAIN_TIMEOUT`] to finish.
+///
+/// TLS, when configured, applies to TCP listeners; Unix sockets always speak
+/// plain HTTP to the local reverse proxy.
+pub async fn serve(
+    app: Router,
+    config: &ServerConfig,
+    supervisor: &TaskSupervisor,
+    inherited: InheritedSockets,
+) -> io::Result<()> {
+    let listeners = listen::bind_all(config, inherited).await?;
+    let shutdown = supervisor.shutdown_signal();
+
+    let acceptor = match &config.tls {
+        Some(tls) => {
+            let cert = ReloadableCert::load(tls)?;
+            let acceptor = cert.acceptor()?;
+            supervisor.spawn("certificate watcher", move || {
+                let cert = Arc::clone(&cert);
+                async move {
+                    cert.watch().await;
+                    Ok(())
+                }
+            });
+            Some(acceptor)
+        }
+        None => None,
+    };
+
+    let mut servers = JoinSet::new();
+    let mut https_port = None;
+    let mut socket_paths = Vec::new();
+    for listener in listeners {
+        let service = app
+            .clone()
+            .into_make_service_with_connect_info::<PeerAddr>();
+        let shutdown = shutdown.clone().wait();
+        match listener {
+            BoundListener::Tcp(listener) => match &acceptor {
+                Some(acceptor) => {
+                    let listener = TlsListener::new(listener, acceptor.clone())?;
+                    let local_addr = listener.local_addr()?;
+                    https_port.get_or_insert(local_addr.port());
+                    tracing::info!("Listening on https://{}", local_addr);
+                    servers.spawn(
+                        axum::serve(listener, service)
+                            .with_graceful_shutdown(shutdown)
+                            .into_future(),
+                    );
+                }
+                None => {
+                    tracing::info!("Listening on http://{}", listener.local_addr()?);
+                    servers.spawn(
+                        axum::serve(listener, service)
+                            .with_graceful_shutdown(shutdown)
+                            .into_future(),
+                    );
+                }
+            },
+            BoundListener::Unix {
+                listener,
+                path,
+                owned,
+            } => {
+                tracing::info!("Listening on unix:{}", path.display());
+                if owned {
+                    socket_paths.push(path);
+                }
+                servers.spawn(
+                    axum::serve(listener, service)
+                        .with_graceful_shutdown(shutdown)
+                        .into_future(),
+                );
+            }
+        }
+    }
+
+    if let Some(tls) = &config.tls
+        && let Some(redirect_address) = &tls.redirect_address
+    {
+        let redirect_listener = TcpListener::bind(redirect_address).await?;
+        tracing::info!(
+            "Redirecting http://{} to HTTPS",
+            redirect_listener.local_addr()?
+        );
+        let redirect = axum::serve(
+            redirect_listener,
+            redirect_router(https_port.unwrap_or(443)),
+        )
+        .with_graceful_shutdown(shutdown.clone().wait());
+        servers.spawn(redirect.into_future());
+    }
+
+    let drained = async {
+        while let Some(result) = servers.join_next().await {
+            result.map_err(io::Error::other)??;
+        }
+        Ok(())
+    };
+    let result = tokio::select! {
+        result = drained => result,
+        _ = async {
+            shutdown.wait().await;
+            tokio::time::sleep(DRAIN_TIMEOUT).await;
+        } => {
+            tracing::warn!("Timed out waiting for open connections to finish");
+            Ok(())
+        }
+    };
+
+    for path in socket_paths {
+        let _ = std::fs::remove_file(path);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ServerConfig {
+        ServerConfig {
+            trusted_proxies: vec!["10.0.0.0/8".parse().unwrap()],
+            ..ServerConfig::default()
+        }
+    }
+
+    fn tcp(addr: &str) -> PeerAddr {
+        PeerAddr::Tcp(addr.parse().unwrap())
+    }
+
+    fn forwarded_for(values: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append(FORWARDED_FOR_HEADER, value.parse().unwrap());
+        }
+        headers
+    }
+
+    fn client_ip(peer: PeerAddr, forwarded: &[&str]) -> Option<IpAddr> {
+        peer.client_ip(&forwarded_for(forwarded), &config())
+    }
+
+    fn ip(ip: &str) -> Option<IpAddr> {
+        Some(ip.parse().unwrap())
+    }
+
+    #[test]
+    fn untrusted_peers_are_the_client_whatever_they_forward() {
+        assert_eq!(
+            client_ip(tcp("203.0.113.7:1234"), &["10.1.1.1"]),
+            ip("203.0.113.7")
+        );
+    }
+
+    #[test]
+    fn trusted_proxies_name_the_client() {
+        let proxy = tcp("10.0.0.2:1234");
+        assert_eq!(client_ip(proxy, &[]), ip("10.0.0.2"));
+        assert_eq!(client_ip(proxy, &["198.51.100.1"]), ip("198.51.100.1"));
+        assert_eq!(
+            client_ip(proxy, &["198.51.100.1:5678, 10.0.0.3"]),
+            ip("198.51.100.1")
+        );
+    }
+
+    #[test]
+    fn spoofed_left_most_entries_are_ignored() {
+        // The client itself sent `10.9.9.9, 192.0.2.1`; the proxy appended its address
+        let proxy = tcp("10.0.0.2:1234");
+        assert_eq!(
+            client_ip(proxy, &["10.9.9.9, 192.0.2.1", "198.51.100.1"]),
+            ip("198.51.100.1")
+        );
+        assert_eq!(client_ip(proxy, &["10.9.9.9, garbage"]), ip("10.0.0.2"));
+    }
+
+    #[test]
+    fn ipv4_mapped_peers_are_treated_as_ipv4() {
+        assert_eq!(
+            client_ip(tcp("[::ffff:10.0.0.2]:1234"), &["198.51.100.1"]),
+            ip("198.51.100.1")
+        );
+        assert_eq!(
+            client_ip(tcp("[::ffff:203.0.113.7]:1234"), &["198.51.100.1"]),
+            ip("203.0.113.7")
+        );
+        assert_eq!(
+            client_ip(tcp("10.0.0.2:1234"), &["::ffff:198.51.100.1"]),
+            ip("198.51.100.1")
+        );
+    }
+
+    #[test]
+    fn unix_socket_peers_have_no_address_unless_forwarded() {
+        assert_eq!(client_ip(PeerAddr::Unix, &[]), None);
+        assert_eq!(
+            client_ip(PeerAddr::Unix, &["198.51.100.1"]),
+            ip("198.51.100.1")
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use std::fs;
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use axum::{
+    Router,
+    extract::Request,
+    http::{HeaderMap, header},
+    response::{IntoResponse, Redirect},
+    serve::Listener,
+};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::{
+    TlsAcceptor,
+    rustls::{
+        self, ServerConfig,
+        crypto::{CryptoProvider, aws_lc_rs},
+        pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+        server::{ClientHello, ResolvesServerCert},
+        sign::CertifiedKey,
+    },
+    server::TlsStream,
+};
+
+use crate::config::TlsConfig;
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+const CERT_WATCH_INTERVAL: Duration = Duration::from_secs(10);
+/// Handshaken connections waiting for the HTTP server to pick them up
+const ACCEPT_BACKLOG: usize = 128;
+
+/// Certificate resolver whose certificate can be swapped while the server runs
+#[derive(Debug)]
+pub struct ReloadableCert {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    provider: Arc<CryptoProvider>,
+    current: RwLock<Arc<CertifiedKey>>,
+}
+
+impl ReloadableCert {
+    pub fn load(config: &TlsConfig) -> io::Result<Arc<Self>> {
+        let provider = Arc::new(aws_lc_rs::default_provider());
+        let certified_key = load_certified_key(&config.cert, &config.key, &provider)?;
+
+        Ok(Arc::new(Self {
+            cert_path: config.cert.clone(),
+            key_path: config.key.clone(),
+            provider,
+            current: RwLock::new(Arc::new(certified_key)),
+        }))
+    }
+
+    pub fn acceptor(self: &Arc<Self>) -> io::Result<TlsAcceptor> {
+        let mut server_config = ServerConfig::builder_with_provider(self.provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(io::Error::other)?
+            .with_no_client_auth()
+            .with_cert_resolver(self.clone());
+        server_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+        Ok(TlsAcceptor::from(Arc::new(server_config)))
+    }
+
+    /// Reload the certificate whenever the PEM files change on disk, e.g. after a
+    /// certbot or acme.sh renewal; runs until cancelled
+    pub async fn watch(&self) {
+        let mut last_modified = self.modified();
+        let mut ticker = tokio::time::interval(CERT_WATCH_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let modified = self.modified();
+            if modified == last_modified {
+                continue;
+            }
+
+            match load_certified_key(&self.cert_path, &self.key_path, &self.provider) {
+                Ok(certified_key) => {
+                    if let Ok(mut current) = self.current.write() {
+                        *current = Arc::new(certified_key);
+                    }
+                    last_modified = modified;
+                    tracing::info!(path = ?self.cert_path, "Reloaded TLS certificate");
+                }
+                // Renewal tools may write the cert and key separately; retry next tick
+                Err(e) => tracing::warn!(error = %e, "Error reloading TLS certificate"),
+            }
+        }
+    }
+
+    fn modified(&self) -> (Option<SystemTime>, Option<SystemTime>) {
+        let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+        (modified(&self.cert_path), modified(&self.key_path))
+    }
+}
+
+impl ResolvesServerCert for ReloadableCert {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        self.current.read().ok().map(|current| current.clone())
+    }
+}
+
+fn load_certified_key(
+    cert_path: &Path,
+    key_path: &Path,
+    provider: &CryptoProvider,
+) -> io::Result<CertifiedKey> {
+    let pem_error = |path: &Path, e: rustls::pki_types::pem::Error| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Failed to read {:?}: {}", path, e),
+        )
+    };
+
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .map_err(|e| pem_error(cert_path, e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| pem_error(cert_path, e))?;
+    if certs.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("No certificates found in {:?}", cert_path),
+        ));
+    }
+    let key = PrivateKeyDer::from_pem_file(key_path).map_err(|e| pem_error(key_path, e))?;
+
+    CertifiedKey::from_der(certs, key, provider).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Certificate and key do not match: {}", e),
+        )
+    })
+}
+
+/// TCP listener that performs TLS handshakes in the background, so a slow client
+/// cannot hold up accepting other connections
+pub struct TlsListener {
+    incoming: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+    local_addr: SocketAddr,
+}
+
+impl TlsListener {
+    pub fn new(listener: TcpListener, acceptor: TlsAcceptor) -> io::Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let (sender, incoming) = mpsc::channel(ACCEPT_BACKLOG);
+
+        tokio::spawn(async move {
+            let mut listener = listener;
+            loop {
+                // Stop accepting once the server has shut down and dropped its end
+                let (stream, addr) = tokio::select! {
+                    _ = sender.closed() => return,
+                    connection = Listener::accept(&mut listener) => connection,
+                };
+                let acceptor = acceptor.clone();
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                        Ok(Ok(stream)) => {
+                            let _ = sender.send((stream, addr)).await;
+                        }
+                        Ok(Err(e)) => tracing::debug!(%addr, error = %e, "TLS handshake failed"),
+                        Err(_) => tracing::debug!(%addr, "TLS handshake timed out"),
+                    }
+                });
+            }
+        });
+
+        Ok(Self {
+            incoming,
+            local_addr,
+        })
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.incoming.recv().await {
+            Some(connection) => connection,
+            // The accept loop only exits after this listener is dropped
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+/// Router for the plain HTTP port that sends everything to HTTPS
+pub fn redirect_router(https_port: u16) -> Router {
+    Router::new().fallback(move |headers: HeaderMap, request: Request| async move {
+        let host = headers
+            .get(header::HOST)
+            .and_then(|value| value.to_str().ok())
+            .map(strip_port)
+            .unwrap_or("localhost");
+        let port = if https_port == 443 {
+            String::new()
+        } else {
+            format!(":{}", https_port)
+        };
+        let path = request
+            .uri()
+            .path_and_query()
+            .map(|pq| pq.as_str())
+            .unwrap_or("/");
+
+        Redirect::permanent(&format!("https://{}{}{}", host, port, path)).into_response()
+    })
+}
+
+fn strip_port(host: &str) -> &str {
+    if host.starts_with('[') {
+        // IPv6 literal, e.g. [::1]:8080
+        host.find(']').map(|end| &host[..=end]).unwrap_or(host)
+    } else {
+        host.rsplit_once(':').map(|(name, _)| name).unwrap_or(host)
+    }
+}
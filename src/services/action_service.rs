@@ -0,0 +1,244 @@
+use std::net::SocketAddr;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+
+use indexmap::IndexMap;
+use reqwest::{Client, Method};
+use tokio::net::UdpSocket;
+use tokio::process::Command;
+
+use crate::config::{ActionKind, ServiceAction};
+use crate::error::{VestaError, VestaResult};
+use crate::http_client;
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_WOL_BROADCAST: &str = "255.255.255.255";
+const DEFAULT_WOL_PORT: u16 = 9;
+/// Most of a failed command's output included in the error
+const MAX_COMMAND_OUTPUT: usize = 500;
+
+/// Runs the actions services declare: Wake-on-LAN, webhooks and whitelisted commands
+pub struct ActionService {
+    http_client: Client,
+}
+
+impl ActionService {
+    pub fn new(http_client: Client) -> Arc<Self> {
+        Arc::new(Self { http_client })
+    }
+
+    /// Run `action`, returning a short description of what was done. Commands
+    /// are looked up in `commands` and run without a shell.
+    pub async fn run(
+        &self,
+        action: &ServiceAction,
+        commands: &IndexMap<String, Vec<String>>,
+    ) -> VestaResult<String> {
+        match &action.kind {
+            ActionKind::Wol { mac, broadcast } => {
+                let target = wol_target(broadcast.as_deref())?;
+                send_magic_packet(mac, target).await?;
+                Ok(format!("Sent a magic packet to {}", mac))
+            }
+            ActionKind::Webhook { url, method, body } => {
+                let status = self
+                    .call_webhook(url, method.as_deref(), body.clone())
+                    .await?;
+                Ok(format!("Webhook answered {}", status))
+            }
+            ActionKind::Command { command } => {
+                let argv = commands.get(command).ok_or_else(|| {
+                    VestaError::Internal(format!("Command '{}' is not in [commands]", command))
+                })?;
+                run_command(command, argv).await?;
+                Ok(format!("Command '{}' finished", command))
+            }
+        }
+    }
+
+    async fn call_webhook(
+        &self,
+        url: &str,
+        method: Option<&str>,
+        body: Option<String>,
+    ) -> VestaResult<reqwest::StatusCode> {
+        let method = match method {
+            Some(method) => Method::from_bytes(method.to_uppercase().as_bytes()).map_err(|_| {
+                VestaError::Internal(format!("Invalid webhook method '{}'", method))
+            })?,
+            None => Method::POST,
+        };
+
+        let mut request = self
+            .http_client
+            .request(method, url)
+            .timeout(WEBHOOK_TIMEOUT);
+        if let Some(body) = body {
+            request = request.body(body);
+        }
+
+        let response = http_client::send(request).await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(VestaError::ApiError {
+                status: reqwest::StatusCode::BAD_GATEWAY,
+                message: format!("Webhook answered {}", status),
+            });
+        }
+        Ok(status)
+    }
+}
+
+/// The broadcast address to send to, `192.168.1.255` or `192.168.1.255:7`
+fn wol_target(broadcast: Option<&str>) -> VestaResult<SocketAddr> {
+    let broadcast = broadcast.unwrap_or(DEFAULT_WOL_BROADCAST);
+    broadcast
+        .parse::<SocketAddr>()
+        .or_else(|_| {
+            broadcast
+                .parse()
+                .map(|ip| SocketAddr::new(ip, DEFAULT_WOL_PORT))
+        })
+        .map_err(|_| VestaError::Internal(format!("Invalid broadcast address '{}'", broadcast)))
+}
+
+/// Six `0xff` bytes followed by the MAC address sixteen times
+async fn send_magic_packet(mac: &str, target: SocketAddr) -> VestaResult<()> {
+    let mac = parse_mac(mac)?;
+    let mut packet = vec![0xff; 6];
+    for _ in 0..16 {
+        packet.extend_from_slice(&mac);
+    }
+
+    let bind = if target.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    let socket = UdpSocket::bind(bind)
+        .await
+        .map_err(|e| VestaError::Internal(format!("Failed to open UDP socket: {}", e)))?;
+    socket
+        .set_broadcast(true)
+        .map_err(|e| VestaError::Internal(format!("Failed to enable broadcast: {}", e)))?;
+    socket
+        .send_to(&packet, target)
+        .await
+        .map_err(|e| VestaError::Internal(format!("Failed to send magic packet: {}", e)))?;
+    Ok(())
+}
+
+/// `aa:bb:cc:dd:ee:ff` or `AA-BB-CC-DD-EE-FF`
+fn parse_mac(mac: &str) -> VestaResult<[u8; 6]> {
+    let invalid = || VestaError::Internal(format!("Invalid MAC address '{}'", mac));
+    let parts: Vec<&str> = mac.split([':', '-']).collect();
+    if parts.len() != 6 {
+        return Err(invalid());
+    }
+
+    let mut bytes = [0; 6];
+    for (byte, part) in bytes.iter_mut().zip(parts) {
+        // `from_str_radix` would also take a sign, as in `+a`
+        if part.len() != 2 || !part.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        *byte = u8::from_str_radix(part, 16).map_err(|_| invalid())?;
+    }
+    Ok(bytes)
+}
+
+async fn run_command(name: &str, argv: &[String]) -> VestaResult<()> {
+    let (program, args) = argv
+        .split_first()
+        .ok_or_else(|| VestaError::Internal(format!("Command '{}' is empty", name)))?;
+
+    let child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| VestaError::Internal(format!("Failed to start '{}': {}", name, e)))?;
+
+    let output = tokio::time::timeout(COMMAND_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| {
+            VestaError::Internal(format!(
+                "Command '{}' timed out after {}s",
+                name,
+                COMMAND_TIMEOUT.as_secs()
+            ))
+        })?
+        .map_err(|e| VestaError::Internal(format!("Failed to run '{}': {}", name, e)))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stderr: String = stderr.trim().chars().take(MAX_COMMAND_OUTPUT).collect();
+        return Err(VestaError::Internal(format!(
+            "Command '{}' failed with {}: {}",
+            name, output.status, stderr
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_mac_accepts_colons_and_dashes() {
+        let expected = [0xaa, 0xbb, 0xcc, 0x01, 0x02, 0xff];
+        assert_eq!(parse_mac("aa:bb:cc:01:02:ff").unwrap(), expected);
+        assert_eq!(parse_mac("AA-BB-CC-01-02-FF").unwrap(), expected);
+    }
+
+    #[test]
+    fn parse_mac_rejects_malformed_addresses() {
+        for mac in [
+            "",
+            "aa:bb:cc:dd:ee",
+            "aa:bb:cc:dd:ee:ff:00",
+            "aabb.ccdd.eeff",
+            "a:bb:cc:dd:ee:fff",
+            "gg:bb:cc:dd:ee:ff",
+            "+a:bb:cc:dd:ee:ff",
+            "aa:bb:cc:dd:ee:",
+        ] {
+            assert!(parse_mac(mac).is_err(), "{mac:?}");
+        }
+    }
+
+    #[test]
+    fn wol_target_defaults_to_broadcast_on_port_9() {
+        assert_eq!(
+            wol_target(None).unwrap(),
+            "255.255.255.255:9".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn wol_target_takes_an_address_with_or_without_port() {
+        assert_eq!(
+            wol_target(Some("192.168.1.255")).unwrap(),
+            "192.168.1.255:9".parse().unwrap()
+        );
+        assert_eq!(
+            wol_target(Some("192.168.1.255:7")).unwrap(),
+            "192.168.1.255:7".parse().unwrap()
+        );
+        assert_eq!(
+            wol_target(Some("[ff02::1]:7")).unwrap(),
+            "[ff02::1]:7".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn wol_target_rejects_host_names() {
+        assert!(wol_target(Some("nas.local")).is_err());
+        assert!(wol_target(Some("192.168.1.255:port")).is_err());
+    }
+}
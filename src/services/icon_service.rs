@@ -0,0 +1,462 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Bytes;
+use moka::future::Cache;
+use reqwest::{Client, Response, Url};
+use sha2::{Digest, Sha256};
+
+use crate::assets::short_hash;
+use crate::error::{VestaError, VestaResult};
+use crate::http_client;
+
+/// Icons larger than this are not cached and get the letter avatar instead
+const MAX_ICON_SIZE: usize = 1024 * 1024;
+/// Memory used for icons kept in memory, on top of the copies on disk
+const MEMORY_CACHE_SIZE: u64 = 16 * 1024 * 1024;
+/// Space the copies on disk may take; the least recently used are removed beyond it
+const DISK_CACHE_SIZE: u64 = 64 * 1024 * 1024;
+/// How much of a page is searched for its favicon
+const MAX_PAGE_SIZE: usize = 512 * 1024;
+/// How long to wait before fetching an icon that failed again
+const FAILURE_TTL: Duration = Duration::from_secs(600);
+
+const DASHBOARD_ICONS_PREFIX: &str = "di:";
+const DASHBOARD_ICONS_URL: &str = "https://cdn.jsdelivr.net/gh/walkxcode/dashboard-icons";
+const SIMPLE_ICONS_PREFIX: &str = "si:";
+const SIMPLE_ICONS_URL: &str = "https://cdn.simpleicons.org";
+/// `img_src = "favicon"` is stored as `favicon:` followed by the page it belongs to
+const FAVICON: &str = "favicon";
+const FAVICON_PREFIX: &str = "favicon:";
+
+#[derive(Clone, Debug)]
+pub struct Icon {
+    pub content_type: &'static str,
+    pub data: Bytes,
+}
+
+/// Identifier of a remote icon, used in `/icons/{hash}` URLs
+pub fn icon_hash(source: &str) -> String {
+    short_hash(&Sha256::digest(source.as_bytes()))
+}
+
+/// The icon source for an `img_src` that links to `href`. The `favicon`
+/// shorthand depends on the page, so it is combined with `href`.
+pub fn icon_source(img_src: &str, href: Option<&str>) -> String {
+    match href {
+        Some(href) if img_src == FAVICON => format!("{}{}", FAVICON_PREFIX, href),
+        _ => img_src.to_string(),
+    }
+}
+
+/// Whether an icon source is fetched and cached by Vesta instead of being linked directly:
+/// a URL, `di:name` (dashboard-icons), `si:name` (simple-icons) or a favicon
+pub fn is_remote_icon(source: &str) -> bool {
+    [
+        "http://",
+        "https://",
+        DASHBOARD_ICONS_PREFIX,
+        SIMPLE_ICONS_PREFIX,
+        FAVICON_PREFIX,
+    ]
+    .iter()
+    .any(|prefix| source.starts_with(prefix))
+}
+
+/// Expand a `di:` or `si:` shorthand to the URL of the icon on its CDN
+fn shorthand_url(source: &str) -> Option<String> {
+    if let Some(name) = source.strip_prefix(DASHBOARD_ICONS_PREFIX) {
+        // `di:jellyfin` is the SVG, `di:jellyfin.png` and `di:jellyfin.webp` the other formats
+        let format = match name.rsplit_once('.') {
+            Some((_, extension @ ("png" | "webp"))) => extension,
+            _ => "svg",
+        };
+        let file = if format == "svg" && !name.ends_with(".svg") {
+            format!("{}.svg", name)
+        } else {
+            name.to_string()
+        };
+        is_icon_name(name).then(|| format!("{}/{}/{}", DASHBOARD_ICONS_URL, format, file))
+    } else if let Some(name) = source.strip_prefix(SIMPLE_ICONS_PREFIX) {
+        // `si:github` is in the brand colour, `si:github/white` in another one
+        is_icon_name(name).then(|| format!("{}/{}", SIMPLE_ICONS_URL, name))
+    } else {
+        Some(source.to_string())
+    }
+}
+
+/// Icon names end up in CDN paths, so they must not be able to leave the icon directory
+fn is_icon_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .split('/')
+            .all(|part| !part.is_empty() && !part.starts_with('.'))
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'/'))
+}
+
+/// Fetches remote icons once and keeps them on disk, so pages do not hot-link
+/// external CDNs
+pub struct IconService {
+    http_client: Client,
+    dir: PathBuf,
+    icons: Cache<String, Icon>,
+    failures: Cache<String, ()>,
+}
+
+impl IconService {
+    pub fn new(http_client: Client, dir: PathBuf) -> Arc<Self> {
+        let icons = Cache::builder()
+            .max_capacity(MEMORY_CACHE_SIZE)
+            .weigher(|_, icon: &Icon| icon.data.len().try_into().unwrap_or(u32::MAX))
+            .build();
+        let failures = Cache::builder()
+            .max_capacity(1000)
+            .time_to_live(FAILURE_TTL)
+            .build();
+
+        Arc::new(Self {
+            http_client,
+            dir,
+            icons,
+            failures,
+        })
+    }
+
+    /// The icon for an icon source, from memory, disk or the network in that order
+    pub async fn get_icon(&self, source: &str) -> VestaResult<Icon> {
+        let hash = icon_hash(source);
+        if self.failures.contains_key(&hash) {
+            return Err(VestaError::Internal(format!(
+                "Icon {} failed to load recently",
+                source
+            )));
+        }
+
+        // Concurrent requests for the same icon share a single fetch
+        match self
+            .icons
+            .try_get_with(hash.clone(), self.load_icon(&hash, source))
+            .await
+        {
+            Ok(icon) => Ok(icon),
+            Err(e) => {
+                tracing::warn!(source, error = %e, "Error fetching icon");
+                self.failures.insert(hash, ()).await;
+                Err(VestaError::Internal(e.to_string()))
+            }
+        }
+    }
+
+    async fn load_icon(&self, hash: &str, source: &str) -> VestaResult<Icon> {
+        let path = self.dir.join(hash);
+        if let Ok(data) = tokio::fs::read(&path).await
+            && let Some(content_type) = sniff_content_type(&data)
+        {
+            // The modification time tells which icons were used last when the
+            // directory grows too large
+            if let Err(e) = touch(&path).await {
+                tracing::debug!(path = %path.display(), error = %e, "Error touching icon");
+            }
+            return Ok(Icon {
+                content_type,
+                data: data.into(),
+            });
+        }
+
+        let icon = self.fetch_icon(source).await?;
+        if let Err(e) = self.store_icon(&path, &icon.data).await {
+            tracing::warn!(path = %path.display(), error = %e, "Error saving icon");
+        }
+        if let Err(e) = prune_dir(&self.dir, DISK_CACHE_SIZE).await {
+            tracing::warn!(dir = %self.dir.display(), error = %e, "Error removing old icons");
+        }
+        Ok(icon)
+    }
+
+    async fn fetch_icon(&self, source: &str) -> VestaResult<Icon> {
+        if let Some(page) = source.strip_prefix(FAVICON_PREFIX) {
+            return self.fetch_favicon(page).await;
+        }
+        let url = shorthand_url(source)
+            .ok_or_else(|| VestaError::Internal(format!("Invalid icon name: {}", source)))?;
+        self.download_icon(&url).await
+    }
+
+    /// Find the icon a page links to with `<link rel="icon">`, falling back to `/favicon.ico`
+    async fn fetch_favicon(&self, page: &str) -> VestaResult<Icon> {
+        let response = http_client::send(self.http_client.get(page))
+            .await?
+            .error_for_status()?;
+        // Relative links are resolved against the page reached after redirects
+        let page_url = response.url().clone();
+        let html = read_body(response, MAX_PAGE_SIZE, page).await?;
+        let html = String::from_utf8_lossy(&html);
+
+        let mut candidates: Vec<Url> = favicon_links(&html)
+            .iter()
+            .filter_map(|href| page_url.join(href).ok())
+            .collect();
+        if let Ok(default) = page_url.join("/favicon.ico") {
+            candidates.push(default);
+        }
+
+        let mut last_error = None;
+        for candidate in candidates {
+            match self.download_icon(candidate.as_str()).await {
+                Ok(icon) => return Ok(icon),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error
+            .unwrap_or_else(|| VestaError::Internal(format!("No favicon found for {}", page))))
+    }
+
+    async fn download_icon(&self, url: &str) -> VestaResult<Icon> {
+        let response = http_client::send(self.http_client.get(url))
+            .await?
+            .error_for_status()?;
+        let data = read_body(response, MAX_ICON_SIZE, url).await?;
+
+        // The declared content type is ignored; servers often get it wrong, and
+        // error pages must not be served as icons
+        let content_type = sniff_content_type(&data)
+            .ok_or_else(|| VestaError::Internal(format!("{} is not an image", url)))?;
+        Ok(Icon {
+            content_type,
+            data: data.into(),
+        })
+    }
+
+    async fn store_icon(&self, path: &Path, data: &[u8]) -> std::io::Result<()> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        // Written under another name first, so a crash never leaves half an icon behind
+        let temp_path = path.with_extension("tmp");
+        let result = match tokio::fs::write(&temp_path, data).await {
+            Ok(()) => tokio::fs::rename(&temp_path, path).await,
+            Err(e) => Err(e),
+        };
+        if result.is_err() {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+        }
+        result
+    }
+}
+
+/// Mark a file as just used
+async fn touch(path: &Path) -> std::io::Result<()> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        std::fs::File::options()
+            .append(true)
+            .open(path)?
+            .set_modified(std::time::SystemTime::now())
+    })
+    .await
+    .map_err(std::io::Error::other)?
+}
+
+/// Remove the least recently modified files in `dir` until the rest fit in `budget` bytes
+async fn prune_dir(dir: &Path, budget: u64) -> std::io::Result<()> {
+    let mut files = Vec::new();
+    let mut total = 0;
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let metadata = entry.metadata().await?;
+        if metadata.is_file() {
+            total += metadata.len();
+            files.push((metadata.modified()?, metadata.len(), entry.path()));
+        }
+    }
+    if total <= budget {
+        return Ok(());
+    }
+
+    files.sort();
+    for (_, size, path) in files {
+        if total <= budget {
+            break;
+        }
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => total -= size,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => total -= size,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// Read a response body, failing if it is larger than `limit`
+async fn read_body(mut response: Response, limit: usize, url: &str) -> VestaResult<Vec<u8>> {
+    let too_large = || VestaError::Internal(format!("{} is larger than {} bytes", url, limit));
+
+    if response
+        .content_length()
+        .is_some_and(|length| length > limit as u64)
+    {
+        return Err(too_large());
+    }
+    // The length header may be missing or wrong, so the body is counted as well
+    let mut data = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if data.len() + chunk.len() > limit {
+            return Err(too_large());
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok(data)
+}
+
+/// The `href`s of `<link>` tags for icons in a page, best first
+fn favicon_links(html: &str) -> Vec<String> {
+    let lowercase = html.to_ascii_lowercase();
+    let mut icons = Vec::new();
+    let mut touch_icons = Vec::new();
+
+    for (start, _) in lowercase.match_indices("<link") {
+        let Some(length) = lowercase[start..].find('>') else {
+            break;
+        };
+        // Attribute names are matched in lowercase, values are taken from the original
+        let tag = &html[start..start + length];
+        let rel = tag_attribute(tag, "rel")
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let Some(href) = tag_attribute(tag, "href") else {
+            continue;
+        };
+        let href = href.replace("&amp;", "&");
+
+        let rel: Vec<&str> = rel.split_ascii_whitespace().collect();
+        if rel.contains(&"icon") {
+            icons.push(href);
+        } else if rel.contains(&"apple-touch-icon") {
+            touch_icons.push(href);
+        }
+    }
+
+    icons.extend(touch_icons);
+    icons
+}
+
+/// The value of an attribute in an HTML tag, quoted or not
+fn tag_attribute(tag: &str, name: &str) -> Option<String> {
+    let lowercase = tag.to_ascii_lowercase();
+    let mut search_from = 0;
+    while let Some(found) = lowercase[search_from..].find(name) {
+        let start = search_from + found;
+        search_from = start + name.len();
+
+        // Skip matches inside other attributes, e.g. `rel` in `data-rel`
+        let preceded_by_space = lowercase[..start]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_ascii_whitespace());
+        let rest = lowercase[search_from..].trim_start();
+        if !preceded_by_space || !rest.starts_with('=') {
+            continue;
+        }
+
+        let value_start = tag.len() - rest.len() + 1;
+        let value = tag[value_start..].trim_start();
+        return Some(
+            match value.chars().next() {
+                Some(quote @ ('"' | '\'')) => value[1..].split(quote).next().unwrap_or_default(),
+                _ => value
+                    .split(|c: char| c.is_ascii_whitespace())
+                    .next()
+                    .unwrap_or_default(),
+            }
+            .to_string(),
+        );
+    }
+    None
+}
+
+/// Work out the image type from the first bytes of the file
+fn sniff_content_type(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if data.starts_with(&[0xff, 0xd8, 0xff]) {
+        Some("image/jpeg")
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else if data.len() >= 12 && &data[4..12] == b"ftypavif" {
+        Some("image/avif")
+    } else if data.starts_with(&[0x00, 0x00, 0x01, 0x00]) {
+        Some("image/x-icon")
+    } else if is_svg(data) {
+        Some("image/svg+xml")
+    } else {
+        None
+    }
+}
+
+fn is_svg(data: &[u8]) -> bool {
+    let head = &data[..data.len().min(1024)];
+    let head = String::from_utf8_lossy(head).to_ascii_lowercase();
+    let head = head.trim_start_matches('\u{feff}').trim_start();
+    head.starts_with('<') && head.contains("<svg") && !head.contains("<html")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("vesta-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_file(dir: &Path, name: &str, size: usize, age: u64) {
+        let path = dir.join(name);
+        std::fs::write(&path, vec![0; size]).unwrap();
+        std::fs::File::options()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(age))
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn prune_dir_removes_the_least_recently_used_files() {
+        let dir = temp_dir("prune");
+        write_file(&dir, "oldest", 100, 300);
+        write_file(&dir, "older", 100, 200);
+        write_file(&dir, "newest", 100, 100);
+
+        prune_dir(&dir, 250).await.unwrap();
+        assert!(!dir.join("oldest").exists());
+        assert!(dir.join("older").exists());
+
+        touch(&dir.join("older")).await.unwrap();
+        prune_dir(&dir, 100).await.unwrap();
+        assert!(dir.join("older").exists());
+        assert!(!dir.join("newest").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn failed_stores_leave_no_temporary_file() {
+        let dir = temp_dir("store");
+        let service = IconService::new(Client::new(), dir.clone());
+        // A directory in the way makes the rename fail
+        let path = dir.join("icon");
+        std::fs::create_dir(&path).unwrap();
+        std::fs::write(path.join("file"), b"x").unwrap();
+
+        assert!(service.store_icon(&path, b"<svg/>").await.is_err());
+        assert!(!path.with_extension("tmp").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
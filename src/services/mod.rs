@@ -1,3 +1,5 @@
+pub mod action_service;
+pub mod icon_service;
 pub mod ping_service;
 pub mod system_stats_service;
 pub mod weather_service;
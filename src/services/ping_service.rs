@@ -4,8 +4,8 @@ use std::time::Duration;
 use moka::future::Cache;
 use reqwest::Client;
 
-use crate::config::PingConfig;
 use crate::error::VestaResult;
+use crate::http_client;
 
 const DEFAULT_PING_TIMEOUT: Duration = Duration::from_secs(5);
 const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(10);
@@ -41,7 +41,7 @@ impl PingService {
         })
     }
 
-    pub async fn check_service(&self, group: &str, title: &str, config: &PingConfig) -> VestaResult<bool> {
+    pub async fn check_service(&self, group: &str, title: &str, url: &str) -> VestaResult<bool> {
         let key = PingKey {
             group: group.to_string(),
             title: title.to_string(),
@@ -51,7 +51,7 @@ impl PingService {
             return Ok(cached.is_up);
         }
 
-        let result = self.perform_ping(config).await;
+        let result = self.perform_ping(url).await;
         let ping_result = PingResult {
             is_up: result.unwrap_or(false),
         };
@@ -59,13 +59,13 @@ impl PingService {
         Ok(ping_result.is_up)
     }
 
-    async fn perform_ping(&self, config: &PingConfig) -> VestaResult<bool> {
-        let response = self
-            .http_client
-            .get(&config.url)
-            .timeout(self.timeout)
-            .send()
-            .await?;
+    async fn perform_ping(&self, url: &str) -> VestaResult<bool> {
+        let response = http_client::send(
+            self.http_client
+                .get(url)
+                .timeout(self.timeout),
+        )
+        .await?;
         Ok(response.status().is_success())
     }
 }
@@ -36,7 +36,7 @@ pub struct SystemStatsService {
 }
 
 impl SystemStatsService {
-    pub fn new(refresh_interval: Duration) -> Arc<Self> {
+    pub fn new() -> Arc<Self> {
         let initial = Arc::new(SystemStatsSnapshot {
             cpu_usage: 0.0,
             memory_usage: 0.0,
@@ -49,16 +49,9 @@ impl SystemStatsService {
             timestamp: Instant::now(),
         });
 
-        let service = Arc::new(Self {
+        Arc::new(Self {
             snapshot: RwLock::new(initial),
-        });
-
-        let service_clone = Arc::clone(&service);
-        tokio::spawn(async move {
-            service_clone.run_background_refresh(refresh_interval).await;
-        });
-
-        service
+        })
     }
 
     pub async fn get_snapshot(&self) -> Arc<SystemStatsSnapshot> {
@@ -71,7 +64,8 @@ impl SystemStatsService {
         *snapshot = Arc::new(new_snapshot);
     }
 
-    async fn run_background_refresh(&self, interval_duration: Duration) {
+    /// Keep the snapshot up to date; runs until cancelled
+    pub async fn run_background_refresh(&self, interval_duration: Duration) {
         let mut ticker = interval(interval_duration);
         self.refresh_now().await;
         loop {
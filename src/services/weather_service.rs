@@ -6,6 +6,7 @@ use reqwest::Client;
 use serde::Deserialize;
 
 use crate::error::{VestaError, VestaResult};
+use crate::http_client;
 
 const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(600);
 
@@ -79,7 +80,7 @@ impl WeatherService {
             config.latitude, config.longitude, temperature_unit
         );
 
-        let response = self.http_client.get(&url).send().await?;
+        let response = http_client::send(self.http_client.get(&url)).await?;
 
         if !response.status().is_success() {
             return Err(VestaError::ApiError {
@@ -0,0 +1,134 @@
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tokio::signal::unix::{SignalKind, signal};
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+
+use crate::error::VestaResult;
+
+const MIN_RESTART_DELAY: Duration = Duration::from_secs(1);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(60);
+/// How long background tasks get to stop once shutdown begins
+const TASK_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Resolves once shutdown has begun; cheap to clone
+#[derive(Clone)]
+pub struct ShutdownSignal {
+    receiver: watch::Receiver<bool>,
+}
+
+impl ShutdownSignal {
+    pub async fn wait(mut self) {
+        // An error means the supervisor is gone, which also means shutting down
+        let _ = self.receiver.wait_for(|shutting_down| *shutting_down).await;
+    }
+}
+
+/// Owns Vesta's background jobs: restarts them with backoff when they fail and
+/// stops them all on shutdown
+pub struct TaskSupervisor {
+    shutdown: watch::Sender<bool>,
+    tasks: Mutex<JoinSet<()>>,
+}
+
+impl TaskSupervisor {
+    pub fn new() -> Self {
+        Self {
+            shutdown: watch::Sender::new(false),
+            tasks: Mutex::new(JoinSet::new()),
+        }
+    }
+
+    /// Run a long-lived job until shutdown. If it returns an error or panics it
+    /// is started again, waiting longer after each consecutive failure.
+    pub fn spawn<F, Fut>(&self, name: &'static str, job: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = VestaResult<()>> + Send + 'static,
+    {
+        let shutdown = self.shutdown_signal();
+        let supervised = async move {
+            let mut delay = MIN_RESTART_DELAY;
+            loop {
+                let started = Instant::now();
+                let attempt = tokio::spawn(job());
+                let abort = attempt.abort_handle();
+
+                tokio::select! {
+                    _ = shutdown.clone().wait() => {
+                        abort.abort();
+                        return;
+                    }
+                    result = attempt => match result {
+                        Ok(Ok(())) => return,
+                        Ok(Err(e)) => tracing::error!(task = name, error = %e, "Background task failed"),
+                        Err(e) => tracing::error!(task = name, error = %e, "Background task crashed"),
+                    },
+                }
+
+                // A task that ran for a while before failing starts over with a short delay
+                if started.elapsed() > MAX_RESTART_DELAY {
+                    delay = MIN_RESTART_DELAY;
+                }
+                tracing::info!(
+                    task = name,
+                    delay_secs = delay.as_secs(),
+                    "Restarting background task"
+                );
+                tokio::select! {
+                    _ = shutdown.clone().wait() => return,
+                    _ = tokio::time::sleep(delay) => {}
+                }
+                delay = (delay * 2).min(MAX_RESTART_DELAY);
+            }
+        };
+
+        if let Ok(mut tasks) = self.tasks.lock() {
+            tasks.spawn(supervised);
+        }
+    }
+
+    pub fn shutdown_signal(&self) -> ShutdownSignal {
+        ShutdownSignal {
+            receiver: self.shutdown.subscribe(),
+        }
+    }
+
+    /// Begin shutting down when SIGTERM or SIGINT arrives
+    pub fn spawn_signal_handler(&self) {
+        let shutdown = self.shutdown.clone();
+        tokio::spawn(async move {
+            let mut terminate = match signal(SignalKind::terminate()) {
+                Ok(terminate) => terminate,
+                Err(e) => {
+                    tracing::error!(error = %e, "Failed to listen for SIGTERM");
+                    return;
+                }
+            };
+            tokio::select! {
+                _ = terminate.recv() => tracing::info!("Received SIGTERM, shutting down"),
+                _ = tokio::signal::ctrl_c() => tracing::info!("Received SIGINT, shutting down"),
+            }
+            shutdown.send_replace(true);
+        });
+    }
+
+    /// Signal shutdown and wait for the background tasks to stop
+    pub async fn shutdown(&self) {
+        self.shutdown.send_replace(true);
+
+        let mut tasks = match self.tasks.lock() {
+            Ok(mut tasks) => std::mem::take(&mut *tasks),
+            Err(_) => return,
+        };
+        let stopped = tokio::time::timeout(TASK_SHUTDOWN_TIMEOUT, async {
+            while tasks.join_next().await.is_some() {}
+        })
+        .await;
+        if stopped.is_err() {
+            tracing::warn!("Timed out waiting for background tasks to stop");
+        }
+    }
+}
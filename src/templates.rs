@@ -1,32 +1,48 @@
 use crate::AppState;
-use crate::config::{Dashboard, Group, Service, Widget};
+use crate::auth::{Identity, login::LoginOptions};
+use crate::config::{
+    AuthMethod, BreakpointValues, Dashboard, GROUP_PATH_SEPARATOR, Group, Responsive, Service,
+    Widget,
+};
 use crate::ping::render_service_indicator;
+use crate::pwa::theme_color;
+use crate::render::{RenderContext, encode_query_value};
+use crate::services::icon_service::icon_source;
+use crate::widgets::layout_item_style;
 use axum::Extension;
 use maud::{DOCTYPE, Markup, html};
 use std::sync::Arc;
 
-fn head() -> Markup {
+fn head(ctx: &RenderContext) -> Markup {
     html! {
         (DOCTYPE)
-        html lang="en" {
+        html
+            lang=(ctx.locale.code())
+            data-theme=(ctx.theme.mode.as_str())
+            data-accent[ctx.theme.accent().is_some()]
+            data-background[ctx.theme.background.is_some()]
+            data-service-worker=(ctx.url("/sw.js"))
+            data-service-worker-scope=(ctx.url("/"))
+            style=(ctx.theme.css_variables()) {
             head {
                 meta charset="utf-8";
                 meta name="viewport" content="width=device-width, initial-scale=1.0";
-                link rel="stylesheet" type="text/css" href="/static/style.css";
-                link rel="preconnect" href="https://fonts.googleapis.com";
-                link rel="preconnect" href="https://fonts.gstatic.com" crossorigin;
-                link href="https://fonts.googleapis.com/css2?family=IBM+Plex+Sans:wght@300;400;500;600;700&family=JetBrains+Mono:wght@400;500;600;700&display=swap" rel="stylesheet";
-                script src="static/htmx.min.js" {}
-                script src="static/app.js" {}
-                title { "Vesta" }
-                style {
-                    "html { scroll-behavior: smooth; }"
-                    "body { font-family: 'IBM Plex Sans', sans-serif; }"
-                    "h1, h2, h3, .font-mono { font-family: 'JetBrains Mono', monospace; }"
-                    ".mobile-menu-overlay { position: fixed; top: 0; left: 0; width: 100%; height: 100%; background: rgba(0,0,0,0.6); z-index: 30; display: none; backdrop-filter: blur(2px); }"
-                    ".mobile-menu-open .mobile-menu-overlay { display: block; }"
-                    ".mobile-menu-open #sidebar { transform: translateX(0) !important; }"
+                // htmx would otherwise inject an inline <style>, which a strict CSP blocks
+                meta name="htmx-config" content=r#"{"includeIndicatorStyles":false}"#;
+                link rel="stylesheet" type="text/css" href=(ctx.asset_url("style.css"));
+                @if ctx.custom_css {
+                    link rel="stylesheet" type="text/css" href=(ctx.url("/custom.css"));
                 }
+                script src=(ctx.asset_url("htmx.min.js")) {}
+                script src=(ctx.asset_url("app.js")) {}
+                @if ctx.custom_js {
+                    script src=(ctx.url("/custom.js")) defer {}
+                }
+                link rel="icon" href=(favicon_url(ctx));
+                link rel="apple-touch-icon" href=(ctx.asset_url("favicon.png"));
+                link rel="manifest" href=(ctx.url("/manifest.webmanifest"));
+                meta name="theme-color" content=(theme_color(ctx));
+                title { (ctx.settings.title) }
             }
         }
     }
@@ -37,7 +53,7 @@ fn mobile_nav_toggle() -> Markup {
         div class="md:hidden fixed top-4 left-4 z-50" {
             button
                 id="mobile-menu-toggle"
-                class="bg-zinc-900 border border-zinc-800 text-zinc-300 hover:text-white p-2 rounded-lg transition-colors duration-150 cursor-pointer" {
+                class="bg-surface-900 border border-surface-800 text-surface-300 hover:text-surface-50 p-2 rounded-lg transition-colors duration-150 cursor-pointer" {
                 svg class="w-5 h-5" fill="none" stroke="currentColor" viewBox="0 0 24 24" {
                     path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M4 6h16M4 12h16M4 18h16" {}
                 }
@@ -46,31 +62,114 @@ fn mobile_nav_toggle() -> Markup {
     }
 }
 
-fn sidebar_wordmark() -> Markup {
+fn favicon_url(ctx: &RenderContext) -> String {
+    match &ctx.settings.favicon {
+        Some(favicon) => ctx.icon_url(favicon),
+        None => ctx.asset_url("favicon.png"),
+    }
+}
+
+fn sidebar_wordmark(ctx: &RenderContext) -> Markup {
+    let settings = &ctx.settings;
+    html! {
+        div class="flex items-center gap-2 mx-4 my-5 pb-5 border-b border-surface-800" {
+            @if let Some(logo) = &settings.logo {
+                img class="w-6 h-6 object-contain" src=(ctx.icon_url(logo)) alt="";
+            }
+            span class="text-lg font-bold tracking-tight text-surface-50 font-mono" {
+                (settings.wordmark)
+            }
+            @if !settings.tagline.is_empty() {
+                span class="text-xs text-surface-500 font-normal mt-0.5" {
+                    (settings.tagline)
+                }
+            }
+        }
+    }
+}
+
+fn sidebar_search_button(ctx: &RenderContext) -> Markup {
     html! {
-        div class="flex items-center gap-2 mx-4 my-5 pb-5 border-b border-zinc-800" {
-            span class="text-lg font-bold tracking-tight text-white" style="font-family: 'JetBrains Mono', monospace;" {
-                "vesta"
+        button
+            id="command-palette-open"
+            type="button"
+            class="mx-4 flex items-center gap-2 px-3 py-1.5 text-xs text-surface-500 bg-surface-900 border border-surface-800 hover:border-accent-500/30 hover:text-surface-300 rounded-md transition-colors duration-150 cursor-pointer" {
+            svg class="w-3.5 h-3.5" fill="none" stroke="currentColor" viewBox="0 0 24 24" {
+                path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M21 21l-4.35-4.35M17 11a6 6 0 11-12 0 6 6 0 0112 0z" {}
             }
-            span class="text-xs text-zinc-500 font-normal mt-0.5" style="font-family: 'IBM Plex Sans', sans-serif;" {
-                "dashboard"
+            span class="flex-1 text-left" { (ctx.locale.t("sidebar.search")) }
+            kbd class="font-mono text-surface-600" { "/" }
+        }
+    }
+}
+
+fn command_palette(ctx: &RenderContext, config: &Dashboard) -> Markup {
+    let bangs: Vec<String> = config
+        .search
+        .providers()
+        .iter()
+        .filter_map(|provider| provider.bang.as_ref())
+        .map(|bang| format!("!{}", bang))
+        .collect();
+    let locale = &ctx.locale;
+    let placeholder = if bangs.is_empty() {
+        locale.t("palette.placeholder").to_string()
+    } else {
+        locale.t_with("palette.placeholder_bangs", &[("bangs", &bangs.join(", "))])
+    };
+
+    html! {
+        div
+            id="command-palette"
+            hidden
+            data-search-url=(ctx.url("/api/search"))
+            class="fixed inset-0 z-50 bg-black/60 backdrop-blur-sm flex items-start justify-center pt-[15vh] px-4" {
+            div role="dialog" aria-label=(locale.t("palette.label"))
+                class="w-full max-w-lg bg-surface-900 border border-surface-800 rounded-lg shadow-2xl overflow-hidden" {
+                input
+                    id="command-palette-input"
+                    type="text"
+                    placeholder=(placeholder)
+                    autocomplete="off"
+                    spellcheck="false"
+                    class="w-full bg-transparent px-4 py-3 text-sm text-surface-100 placeholder:text-surface-500 border-b border-surface-800 focus:outline-none";
+                ul id="command-palette-results" role="listbox" class="max-h-80 overflow-y-auto py-1" {}
+                div class="px-4 py-2 border-t border-surface-800 text-xs text-surface-500 font-mono flex gap-4" {
+                    span { (locale.t("palette.select")) }
+                    span { (locale.t("palette.open")) }
+                    span { (locale.t("palette.close")) }
+                }
+            }
+        }
+    }
+}
+
+/// Shown by `app.js` in place of widgets that cannot be loaded
+fn offline_indicator(ctx: &RenderContext) -> Markup {
+    html! {
+        template id="offline-indicator" {
+            div data-offline class="flex flex-col items-center justify-center h-full gap-1.5 text-xs text-surface-500 font-mono" {
+                svg class="w-5 h-5" fill="none" stroke="currentColor" viewBox="0 0 24 24" {
+                    path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M3 3l18 18M8.5 16.5a5 5 0 017 0M5 13a10 10 0 015.2-2.8M19 13a10 10 0 00-2.3-1.7M12 20h.01" {}
+                }
+                (ctx.locale.t("page.offline"))
             }
         }
     }
 }
 
-fn sidebar_navigation(config: &Dashboard) -> Markup {
+fn sidebar_navigation(ctx: &RenderContext, config: &Dashboard) -> Markup {
     html! {
         nav class="flex-1 mt-4" {
-            p class="text-xs font-medium text-zinc-500 uppercase tracking-widest mb-3 px-4" { "Groups" }
+            p class="text-xs font-medium text-surface-500 uppercase tracking-widest mb-3 px-4" { (ctx.locale.t("sidebar.groups")) }
             div class="space-y-0.5" {
                 @for (group_id, group_config) in &config.groups {
                     a href=(format!("#{}", group_id))
-                      class="flex items-center px-4 py-2 text-sm text-zinc-400 hover:bg-zinc-800/60 hover:text-zinc-100 rounded-md transition-colors duration-150 cursor-pointer group" {
-                        span class="w-1.5 h-1.5 rounded-full bg-violet-500/50 group-hover:bg-violet-400 mr-3 flex-shrink-0 transition-colors duration-150" {}
+                      class="flex items-center px-4 py-2 text-sm text-surface-400 hover:bg-surface-800/60 hover:text-surface-100 rounded-md transition-colors duration-150 cursor-pointer group" {
+                        span class="w-1.5 h-1.5 rounded-full bg-accent-500/50 group-hover:bg-accent-400 mr-3 flex-shrink-0 transition-colors duration-150" {}
                         span class="flex-1 truncate" { (group_config.name) }
-                        span class="text-xs text-zinc-600 group-hover:text-zinc-400 transition-colors duration-150 font-mono" {
-                            (group_config.services.len())
+                        span class="text-xs text-surface-600 group-hover:text-surface-400 transition-colors duration-150 font-mono" {
+                            (group_config.all_services().count())
                         }
                     }
                 }
@@ -79,102 +178,155 @@ fn sidebar_navigation(config: &Dashboard) -> Markup {
     }
 }
 
-fn sidebar_status(config: &Dashboard) -> Markup {
-    let total_services = config
-        .groups
-        .values()
-        .map(|g| g.services.len())
-        .sum::<usize>();
-    let services_with_ping = config
-        .groups
-        .values()
-        .flat_map(|g| &g.services)
-        .filter(|s| s.ping.is_some())
-        .count();
+fn sidebar_tags(ctx: &RenderContext, config: &Dashboard) -> Markup {
+    let tags = config.tags();
+    html! {
+        @if !tags.is_empty() {
+            div class="mt-4 pt-4 border-t border-surface-800" {
+                p class="text-xs font-medium text-surface-500 uppercase tracking-widest mb-3 px-4" { (ctx.locale.t("sidebar.tags")) }
+                div class="px-4 flex flex-wrap gap-1.5" {
+                    @for tag in tags {
+                        button
+                            type="button"
+                            data-tag-filter=(tag)
+                            aria-pressed="false"
+                            class="px-2 py-0.5 text-xs font-mono text-surface-400 bg-surface-900 border border-surface-800 rounded-full hover:border-accent-500/40 hover:text-surface-200 aria-pressed:bg-accent-500/20 aria-pressed:border-accent-500/40 aria-pressed:text-accent-200 transition-colors duration-150 cursor-pointer" {
+                            (tag)
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn sidebar_status(ctx: &RenderContext, config: &Dashboard) -> Markup {
+    let total_services = config.all_services().count();
+    let services_with_ping = config.all_services().filter(|s| s.ping.is_some()).count();
 
     html! {
-        div class="mt-auto pt-4 border-t border-zinc-800" {
-            p class="text-xs font-medium text-zinc-500 uppercase tracking-widest mb-3 px-4" { "Status" }
+        div class="mt-auto pt-4 border-t border-surface-800" {
+            p class="text-xs font-medium text-surface-500 uppercase tracking-widest mb-3 px-4" { (ctx.locale.t("sidebar.status")) }
             div class="px-4 space-y-2" {
                 div class="flex justify-between text-xs" {
-                    span class="text-zinc-500" { "Services" }
-                    span class="text-zinc-300 font-mono" { (total_services) }
+                    span class="text-surface-500" { (ctx.locale.t("sidebar.services")) }
+                    span class="text-surface-300 font-mono" { (total_services) }
                 }
                 div class="flex justify-between text-xs" {
-                    span class="text-zinc-500" { "Monitored" }
-                    span class="text-zinc-300 font-mono" { (services_with_ping) }
+                    span class="text-surface-500" { (ctx.locale.t("sidebar.monitored")) }
+                    span class="text-surface-300 font-mono" { (services_with_ping) }
                 }
                 div class="flex justify-between text-xs" {
-                    span class="text-zinc-500" { "Groups" }
-                    span class="text-zinc-300 font-mono" { (config.groups.len()) }
+                    span class="text-surface-500" { (ctx.locale.t("sidebar.groups")) }
+                    span class="text-surface-300 font-mono" { (config.all_groups().len()) }
+                }
+            }
+        }
+    }
+}
+
+fn sidebar_account(ctx: &RenderContext, config: &Dashboard, identity: &Identity) -> Markup {
+    html! {
+        @if config.auth.enabled {
+            div class="pt-4 mt-4 border-t border-surface-800 px-4 flex items-center justify-between gap-2" {
+                span class="text-xs text-surface-400 truncate font-mono" { (identity.username) }
+                @if config.auth.has_method(AuthMethod::Session) || config.auth.oidc().is_some() {
+                    form method="post" action=(ctx.url("/logout")) {
+                        button type="submit" class="text-xs text-surface-500 hover:text-surface-200 transition-colors duration-150 cursor-pointer" {
+                            (ctx.locale.t("sidebar.sign_out"))
+                        }
+                    }
                 }
             }
         }
     }
 }
 
-fn sidebar(config: &Dashboard) -> Markup {
+fn sidebar(ctx: &RenderContext, config: &Dashboard, identity: &Identity) -> Markup {
     html! {
         aside
             id="sidebar"
-            class="w-56 bg-zinc-950 border-r border-zinc-800/60 p-3 fixed h-full overflow-y-auto z-40 -translate-x-full md:translate-x-0 transition-transform duration-300 ease-in-out" {
+            class="w-56 bg-surface-950 border-r border-surface-800/60 p-3 fixed h-full overflow-y-auto z-40 -translate-x-full md:translate-x-0 transition-transform duration-300 ease-in-out" {
             div class="flex flex-col h-full" {
                 div class="md:hidden flex justify-end mb-2" {
                     button
                         id="mobile-menu-close"
-                        class="p-2 text-zinc-500 hover:text-zinc-200 transition-colors duration-150 cursor-pointer" {
+                        class="p-2 text-surface-500 hover:text-surface-200 transition-colors duration-150 cursor-pointer" {
                         svg class="w-5 h-5" fill="none" stroke="currentColor" viewBox="0 0 24 24" {
                             path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M6 18L18 6M6 6l12 12" {}
                         }
                     }
                 }
-                (sidebar_wordmark())
-                (sidebar_navigation(config))
-                (sidebar_status(config))
+                (sidebar_wordmark(ctx))
+                (sidebar_search_button(ctx))
+                (sidebar_navigation(ctx, config))
+                (sidebar_tags(ctx, config))
+                (sidebar_status(ctx, config))
+                (sidebar_account(ctx, config, identity))
             }
         }
     }
 }
 
-fn main_header() -> Markup {
+fn main_header(ctx: &RenderContext) -> Markup {
+    let settings = &ctx.settings;
     html! {
         header class="mb-8" {
-            h1 class="text-2xl font-semibold text-zinc-100 tracking-tight mb-1" style="font-family: 'JetBrains Mono', monospace;" {
-                "Services"
+            h1 class="text-2xl font-semibold text-surface-100 tracking-tight mb-1" {
+                (settings.header)
             }
-            p class="text-sm text-zinc-500 flex items-center gap-1.5" {
-                svg class="w-4 h-4 text-zinc-600" fill="none" stroke="currentColor" viewBox="0 0 24 24" {
-                    path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M5 12h14M12 5l7 7-7 7" {}
+            @if !settings.subtitle.is_empty() {
+                p class="text-sm text-surface-500 flex items-center gap-1.5" {
+                    svg class="w-4 h-4 text-surface-600" fill="none" stroke="currentColor" viewBox="0 0 24 24" {
+                        path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M5 12h14M12 5l7 7-7 7" {}
+                    }
+                    (settings.subtitle)
+                }
+            }
+        }
+    }
+}
+
+fn main_footer(ctx: &RenderContext) -> Markup {
+    html! {
+        @if !ctx.settings.footer_links.is_empty() {
+            footer class="container mx-auto mt-12 pt-4 border-t border-surface-800 flex flex-wrap gap-x-6 gap-y-2 text-xs" {
+                @for link in &ctx.settings.footer_links {
+                    a href=(link.url) target="_blank" rel="noreferrer"
+                        class="text-surface-500 hover:text-accent-400 transition-colors duration-150" {
+                        (link.title)
+                    }
                 }
-                "Self-hosted applications"
             }
         }
     }
 }
 
 fn main_content(
+    ctx: &RenderContext,
     config: &Dashboard,
     widget_registry: &crate::widget_system::WidgetRegistry,
 ) -> Markup {
     html! {
         div class="flex-1 ml-0 md:ml-56 px-6 md:px-10 py-8 min-h-full" {
-            (main_header())
+            (main_header(ctx))
             main class="container mx-auto my-4 gap-4 flex flex-wrap justify-center h-full lg:justify-start" {
                 @for (id, group_config) in &config.groups {
-                    (group(id, group_config, widget_registry))
+                    (group(ctx, id, group_config, widget_registry))
                 }
             }
+            (main_footer(ctx))
         }
     }
 }
 
-fn error_page(error_message: &str) -> Markup {
+fn error_page(ctx: &RenderContext, error_message: &str) -> Markup {
     html! {
-        (head())
-        body class="min-h-full text-white bg-zinc-950 flex items-center justify-center" {
+        (head(ctx))
+        body class="min-h-full text-surface-50 bg-surface-950 flex items-center justify-center" {
             div class="text-center" {
-                h1 class="text-xl font-semibold mb-3 text-zinc-100" style="font-family: 'JetBrains Mono', monospace;" {
-                    "Configuration Error"
+                h1 class="text-xl font-semibold mb-3 text-surface-100" {
+                    (ctx.locale.t("page.config_error"))
                 }
                 p class="text-red-400 text-sm" { (error_message) }
             }
@@ -182,53 +334,126 @@ fn error_page(error_message: &str) -> Markup {
     }
 }
 
-fn service_card_image(img_src: &str, title: &str) -> Markup {
+pub fn login_page(
+    ctx: &RenderContext,
+    options: &LoginOptions,
+    next: &str,
+    error: Option<&str>,
+) -> Markup {
+    html! {
+        (head(ctx))
+        body class="min-h-full text-surface-50 bg-surface-950 flex items-center justify-center" {
+            div class="w-full max-w-xs bg-card border border-surface-800 rounded-lg p-6 space-y-4" {
+                (sidebar_wordmark(ctx))
+                @if options.password {
+                    (login_password_form(ctx, next))
+                }
+                @if let Some(provider) = &options.oidc_provider {
+                    a href=(ctx.url(&format!("/auth/oidc/login?next={}", encode_query_value(next))))
+                        class="block w-full text-center bg-surface-800/60 border border-surface-700/50 hover:border-accent-500/30 text-surface-200 text-sm font-medium rounded-md py-2 transition-colors duration-150 cursor-pointer" {
+                        (ctx.locale.t_with("login.sign_in_with", &[("provider", provider)]))
+                    }
+                }
+                @if let Some(error) = error {
+                    p class="text-red-400 text-xs" { (error) }
+                }
+            }
+        }
+    }
+}
+
+fn login_password_form(ctx: &RenderContext, next: &str) -> Markup {
+    html! {
+        form method="post" action=(ctx.url("/login")) class="space-y-4" {
+            input type="hidden" name="next" value=(next);
+            div class="space-y-1.5" {
+                label for="username" class="text-xs font-medium text-surface-500 uppercase tracking-widest" { (ctx.locale.t("login.username")) }
+                input id="username" name="username" type="text" autocomplete="username" required autofocus
+                    class="w-full bg-surface-950 border border-surface-800 rounded-md px-3 py-2 text-sm text-surface-100 focus:outline-none focus:border-accent-500/60";
+            }
+            div class="space-y-1.5" {
+                label for="password" class="text-xs font-medium text-surface-500 uppercase tracking-widest" { (ctx.locale.t("login.password")) }
+                input id="password" name="password" type="password" autocomplete="current-password" required
+                    class="w-full bg-surface-950 border border-surface-800 rounded-md px-3 py-2 text-sm text-surface-100 focus:outline-none focus:border-accent-500/60";
+            }
+            button type="submit"
+                class="w-full bg-accent-500/20 border border-accent-500/40 hover:bg-accent-500/30 text-accent-200 text-sm font-medium rounded-md py-2 transition-colors duration-150 cursor-pointer" {
+                (ctx.locale.t("login.sign_in"))
+            }
+        }
+    }
+}
+
+fn service_card_image(ctx: &RenderContext, img_src: &str, title: &str) -> Markup {
     html! {
         img class="object-contain w-6 h-6 md:w-7 md:h-7 mb-1 md:mb-0 md:my-2 !mt-0 opacity-90"
-            src=(img_src)
+            src=(ctx.icon_url(img_src))
             alt=(title);
     }
 }
 
-fn service_card_title(title: &str) -> Markup {
+fn service_card_title(title: &str, description: Option<&str>) -> Markup {
     html! {
-        p class="text-center text-xs text-zinc-300 font-medium leading-tight" { (title) }
+        div class="min-w-0 text-center" {
+            p class="text-xs text-surface-300 font-medium leading-tight" { (title) }
+            @if let Some(description) = description {
+                p class="hidden md:block mt-0.5 text-[11px] text-surface-500 leading-tight truncate" { (description) }
+            }
+        }
     }
 }
 
 fn render_widget_card(
+    ctx: &RenderContext,
     group_id: &str,
     service: &Service,
     widget: &Widget,
     widget_registry: &crate::widget_system::WidgetRegistry,
 ) -> Markup {
-    widget_registry.render_widget(group_id, service, widget)
+    widget_registry.render_widget(ctx, group_id, service, widget)
 }
 
-fn render_service_card(group_id: &str, service_info: &Service) -> Markup {
-    let img_src = service_info.img_src.as_deref().unwrap_or_default();
-    let href = service_info.href.as_deref().unwrap_or_default();
-    let width = service_info.width.unwrap_or(1);
-    let height = service_info.height.unwrap_or(1);
+fn render_service_card(ctx: &RenderContext, group_id: &str, service_info: &Service) -> Markup {
+    let img_src = icon_source(
+        service_info.img_src.as_deref().unwrap_or_default(),
+        service_info.href.as_deref(),
+    );
+    let href = ctx.service_href(service_info).unwrap_or_default();
     let has_ping = service_info.ping.is_some();
 
-    let col_class = if width > 1 {
-        format!(" sm:col-span-{}", width)
-    } else {
-        String::new()
-    };
+    // Action buttons cannot go inside the link, so both sit in the grid item
+    html! {
+        div class="layout-item relative" style=[layout_item_style(service_info.width, service_info.height)] {
+            a href=(href)
+              target="_blank"
+              rel="noreferrer"
+              class="relative h-full flex flex-col xl:flex-row p-3 md:p-4 justify-center md:justify-between items-center text-xs bg-card border border-surface-800 rounded-lg hover:border-accent-500/40 hover:bg-surface-800/80 transition-all duration-150 cursor-pointer"
+              title=[service_info.description.as_deref()] {
+                (service_card_image(ctx, &img_src, &service_info.title))
+                (service_card_title(&service_info.title, service_info.description.as_deref()))
+                @if has_ping {
+                    div class="absolute top-2 right-2 xl:static xl:top-auto xl:right-auto" {
+                        (render_service_indicator(ctx, group_id, &service_info.title))
+                    }
+                }
+            }
+            @if !service_info.actions.is_empty() {
+                (service_card_actions(ctx, group_id, service_info))
+            }
+        }
+    }
+}
 
+/// Buttons for the service's actions, which `app.js` posts to the API
+fn service_card_actions(ctx: &RenderContext, group_id: &str, service_info: &Service) -> Markup {
     html! {
-        a href=(href)
-          target="_blank"
-          rel="noreferrer"
-          class=(format!("relative h-full flex flex-col xl:flex-row p-3 md:p-4 justify-center md:justify-between items-center text-xs bg-zinc-900 border border-zinc-800 rounded-lg hover:border-violet-500/40 hover:bg-zinc-800/80 transition-all duration-150 cursor-pointer{}", col_class))
-          style=(format!("grid-row: span {} / span {};", height, height)) {
-            (service_card_image(img_src, &service_info.title))
-            (service_card_title(&service_info.title))
-            @if has_ping {
-                div class="absolute top-2 right-2 xl:static xl:top-auto xl:right-auto" {
-                    (render_service_indicator(group_id, &service_info.title))
+        div class="absolute bottom-1.5 left-1.5 flex gap-1" {
+            @for action in &service_info.actions {
+                button
+                    type="button"
+                    data-action-url=(ctx.action_url(group_id, &service_info.title, &action.name))
+                    class="px-1.5 py-0.5 text-[10px] font-mono text-surface-400 bg-surface-900 border border-surface-800 rounded hover:border-accent-500/40 hover:text-surface-100 disabled:opacity-50 data-[result=ok]:border-green-500/50 data-[result=error]:border-red-500/50 transition-colors duration-150 cursor-pointer" {
+                    (action.name)
                 }
             }
         }
@@ -237,52 +462,85 @@ fn render_service_card(group_id: &str, service_info: &Service) -> Markup {
 
 fn group_header(group_name: &str) -> Markup {
     html! {
-        div class="mb-5 flex items-center gap-3" {
-            span class="text-xs font-medium text-violet-400 uppercase tracking-widest" style="font-family: 'JetBrains Mono', monospace;" {
+        summary class="mb-5 flex items-center gap-3 list-none cursor-pointer select-none [&::-webkit-details-marker]:hidden" {
+            svg class="w-3 h-3 text-surface-500 [details[open]>summary>&]:rotate-90 transition-transform duration-150" fill="none" stroke="currentColor" viewBox="0 0 24 24" {
+                path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M9 5l7 7-7 7" {}
+            }
+            span class="text-xs font-medium text-accent-400 uppercase tracking-widest font-mono" {
                 (group_name)
             }
-            div class="flex-1 h-px bg-zinc-800" {}
+            div class="flex-1 h-px bg-surface-800" {}
         }
     }
 }
 
 fn group_grid(
+    ctx: &RenderContext,
     group_id: &str,
     group_config: &Group,
     widget_registry: &crate::widget_system::WidgetRegistry,
 ) -> Markup {
     let has_widget = group_config.services.iter().any(|s| s.widget.is_some());
     let base_cols = if has_widget { 1 } else { 2 };
+    // A single number leaves phones at one or two columns
+    let columns = match group_config.columns {
+        Responsive::Fixed(columns) => Responsive::Breakpoints(BreakpointValues {
+            sm: Some(columns),
+            ..Default::default()
+        }),
+        columns => columns,
+    };
     html! {
-        div class=(format!("grid auto-rows-[5rem] grid-cols-{} sm:grid-cols-{} gap-2 md:gap-3 items-stretch", base_cols, &group_config.columns)) {
+        div class="layout-grid grid auto-rows-[5rem] gap-2 md:gap-3 items-stretch"
+            style=(columns.css_variables("columns", base_cols)) {
             @for service in &group_config.services {
-                (render_service_or_widget(group_id, service, widget_registry))
+                (render_service_or_widget(ctx, group_id, service, widget_registry))
             }
         }
     }
 }
 
 fn render_service_or_widget(
+    ctx: &RenderContext,
     group_id: &str,
     service: &Service,
     widget_registry: &crate::widget_system::WidgetRegistry,
 ) -> Markup {
-    if let Some(widget) = &service.widget {
-        render_widget_card(group_id, service, widget, widget_registry)
+    let card = if let Some(widget) = &service.widget {
+        render_widget_card(ctx, group_id, service, widget, widget_registry)
     } else {
-        render_service_card(group_id, service)
+        render_service_card(ctx, group_id, service)
+    };
+
+    // `contents` keeps the card a grid item, while the tag filter can hide it
+    html! {
+        div class="contents" data-service data-tags=(service.tags.join(" ")) {
+            (card)
+        }
     }
 }
 
+/// A group as a section that folds away when its header is clicked, with its
+/// child groups nested below its services. `app.js` remembers which are open.
 fn group(
+    ctx: &RenderContext,
     group_id: &str,
     config: &Group,
     widget_registry: &crate::widget_system::WidgetRegistry,
 ) -> Markup {
     html! {
-        div id=(group_id) class="container scroll-mt-6" {
+        details id=(group_id) class="container scroll-mt-6" data-group open[!config.collapsed] {
             (group_header(&config.name))
-            (group_grid(group_id, config, widget_registry))
+            @if !config.services.is_empty() {
+                (group_grid(ctx, group_id, config, widget_registry))
+            }
+            @if !config.groups.is_empty() {
+                div class="mt-6 pl-4 border-l border-surface-800 space-y-6" {
+                    @for (child_id, child_config) in &config.groups {
+                        (group(ctx, &format!("{}{}{}", group_id, GROUP_PATH_SEPARATOR, child_id), child_config, widget_registry))
+                    }
+                }
+            }
         }
     }
 }
@@ -291,28 +549,34 @@ fn load_dashboard_config(
     state: &AppState,
 ) -> Result<std::sync::RwLockReadGuard<'_, Dashboard>, String> {
     if let Err(e) = state.reload_config() {
-        eprintln!("Error reloading config: {}", e);
+        tracing::error!(error = %e, "Error reloading config");
     }
 
     state.get_config_manager().read_config().map_err(|e| {
-        eprintln!("Error getting config: {}", e);
+        tracing::error!(error = %e, "Error getting config");
         e.to_string()
     })
 }
 
-pub async fn dashboard(Extension(state): Extension<Arc<AppState>>) -> Markup {
+pub async fn dashboard(
+    Extension(state): Extension<Arc<AppState>>,
+    Extension(identity): Extension<Identity>,
+    Extension(ctx): Extension<RenderContext>,
+) -> Markup {
     let config = match load_dashboard_config(&state) {
-        Ok(config) => config,
-        Err(error_message) => return error_page(&error_message),
+        Ok(config) => config.visible_to(&identity),
+        Err(error_message) => return error_page(&ctx, &error_message),
     };
 
     html! {
-        (head())
-        body class="min-h-full text-white bg-zinc-950 flex" {
+        (head(&ctx))
+        body class="min-h-full text-surface-50 bg-surface-950 flex" {
             div class="mobile-menu-overlay" {}
             (mobile_nav_toggle())
-            (sidebar(&config))
-            (main_content(&config, state.get_widget_registry()))
+            (sidebar(&ctx, &config, &identity))
+            (main_content(&ctx, &config, state.get_widget_registry()))
+            (command_palette(&ctx, &config))
+            (offline_indicator(&ctx))
         }
     }
 }
@@ -1,15 +1,33 @@
 use async_trait::async_trait;
 use axum::response::IntoResponse;
 use maud::Markup;
+use moka::{Expiry, future::Cache};
 use serde::Deserialize;
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use crate::{
     AppState,
+    auth::Identity,
     config::{Service, Widget},
     error::{VestaError, VestaResult},
+    render::RenderContext,
+    widgets::{widget_error, widget_loader, widget_stale},
 };
 
+/// Widget config key that overrides how often the widget is reloaded, in seconds
+const REFRESH_INTERVAL_KEY: &str = "refresh_interval";
+const MAX_REFRESH_INTERVAL: Duration = Duration::from_secs(3600);
+/// Most widget responses kept across all widgets
+const CACHE_CAPACITY: u64 = 1000;
+/// How long the last response of a widget with a cache TTL is kept, to show in
+/// its place when it fails
+const STALE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
 /// Query parameters for widget requests
 #[derive(Deserialize, Debug, Clone)]
 pub struct WidgetQuery {
@@ -23,12 +41,48 @@ pub trait WidgetHandler: Send + Sync {
     /// The name of the widget (should match the widget name in config)
     fn name(&self) -> &'static str;
 
-    /// Render the widget as HTML
-    fn render(&self, group_id: &str, service: &Service) -> Markup;
+    /// How often the dashboard reloads the widget, unless the service sets
+    /// `refresh_interval`; `None` loads it once
+    fn refresh_interval(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Shortest `refresh_interval` a service may set, to spare upstream APIs
+    fn min_refresh_interval(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+
+    /// How long a response of `handle_request` is reused for the same service,
+    /// config and language; `None` never reuses it
+    fn cache_ttl(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Classes added to the widget's container, e.g. for scrolling
+    fn container_classes(&self) -> &'static str {
+        ""
+    }
+
+    /// Render the widget as HTML, by default a container that loads
+    /// `handle_request` and reloads it every refresh interval
+    fn render(&self, ctx: &RenderContext, group_id: &str, service: &Service) -> Markup {
+        widget_loader(
+            ctx,
+            self.name(),
+            group_id,
+            service,
+            self.container_classes(),
+            service_refresh_interval(self, service),
+        )
+    }
 
     /// Handle API requests for this widget
-    async fn handle_request(&self, state: Arc<AppState>, query: WidgetQuery)
-    -> VestaResult<Markup>;
+    async fn handle_request(
+        &self,
+        state: Arc<AppState>,
+        ctx: &RenderContext,
+        query: WidgetQuery,
+    ) -> VestaResult<Markup>;
 
     /// Validate widget configuration
     fn validate_config(&self, _widget: &Widget) -> VestaResult<()> {
@@ -37,15 +91,138 @@ pub trait WidgetHandler: Send + Sync {
     }
 }
 
+/// The service's `refresh_interval`, kept within what the widget allows, or the
+/// widget's default when it sets none or an invalid one
+fn service_refresh_interval<W: WidgetHandler + ?Sized>(
+    handler: &W,
+    service: &Service,
+) -> Option<Duration> {
+    service
+        .widget
+        .as_ref()
+        .and_then(|widget| widget.config.as_ref())
+        .and_then(|config| config.get(REFRESH_INTERVAL_KEY))
+        .and_then(|value| value.parse().ok())
+        .map(|secs| {
+            Duration::from_secs(secs).clamp(handler.min_refresh_interval(), MAX_REFRESH_INTERVAL)
+        })
+        .or_else(|| handler.refresh_interval())
+}
+
+/// Out-of-range intervals used to be accepted, so they are only warned about
+/// and clamped when the widget is scheduled
+fn validate_refresh_interval(
+    handler: &dyn WidgetHandler,
+    service: &Service,
+    widget: &Widget,
+) -> VestaResult<()> {
+    let Some(value) = widget
+        .config
+        .as_ref()
+        .and_then(|config| config.get(REFRESH_INTERVAL_KEY))
+    else {
+        return Ok(());
+    };
+
+    let interval = value
+        .parse()
+        .map(Duration::from_secs)
+        .map_err(|_| VestaError::Internal("refresh_interval must be a number".to_string()))?;
+    let min = handler.min_refresh_interval();
+    let clamped = interval.clamp(min, MAX_REFRESH_INTERVAL);
+    if clamped != interval {
+        tracing::warn!(
+            service = %service.title,
+            widget = %widget.name,
+            refresh_interval = interval.as_secs(),
+            used = clamped.as_secs(),
+            "refresh_interval must be between {} and {} seconds, using {} instead",
+            min.as_secs(),
+            MAX_REFRESH_INTERVAL.as_secs(),
+            clamped.as_secs()
+        );
+    }
+    Ok(())
+}
+
+/// A widget response is shared by everyone asking for the same service, as long
+/// as its config, the language and the prefix its URLs are built with are the same
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    widget: String,
+    group: String,
+    title: String,
+    config: u64,
+    locale: &'static str,
+    base_path: String,
+}
+
+impl CacheKey {
+    fn new(widget: &str, query: &WidgetQuery, service: &Service, ctx: &RenderContext) -> Self {
+        let mut config: Vec<_> = service
+            .widget
+            .as_ref()
+            .and_then(|widget| widget.config.as_ref())
+            .into_iter()
+            .flatten()
+            .collect();
+        config.sort();
+        let mut hasher = DefaultHasher::new();
+        config.hash(&mut hasher);
+
+        Self {
+            widget: widget.to_string(),
+            group: query.group.clone(),
+            title: query.title.clone(),
+            config: hasher.finish(),
+            locale: ctx.locale.code(),
+            base_path: ctx.base_path.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct CachedResponse {
+    markup: Markup,
+    fetched_at: Instant,
+    ttl: Duration,
+}
+
+/// Expires each fresh response after the TTL of its widget
+struct WidgetExpiry;
+
+impl Expiry<CacheKey, CachedResponse> for WidgetExpiry {
+    fn expire_after_create(
+        &self,
+        _key: &CacheKey,
+        value: &CachedResponse,
+        _created_at: Instant,
+    ) -> Option<Duration> {
+        Some(value.ttl)
+    }
+}
+
 /// Widget registry that manages all available widgets
 pub struct WidgetRegistry {
     widgets: HashMap<String, Box<dyn WidgetHandler>>,
+    /// Responses still within their widget's cache TTL
+    fresh: Cache<CacheKey, CachedResponse>,
+    /// The last response of each cached widget, shown as stale when it fails
+    last: Cache<CacheKey, CachedResponse>,
 }
 
 impl WidgetRegistry {
     pub fn new() -> Self {
         Self {
             widgets: HashMap::new(),
+            fresh: Cache::builder()
+                .max_capacity(CACHE_CAPACITY)
+                .expire_after(WidgetExpiry)
+                .build(),
+            last: Cache::builder()
+                .max_capacity(CACHE_CAPACITY)
+                .time_to_live(STALE_TTL)
+                .build(),
         }
     }
 
@@ -68,22 +245,22 @@ impl WidgetRegistry {
 
     /// Validate all widgets in a configuration
     pub fn validate_widgets(&self, config: &crate::config::Dashboard) -> VestaResult<()> {
-        for group in config.groups.values() {
-            for service in &group.services {
-                if let Some(widget_config) = &service.widget {
-                    if let Some(widget) = self.get(&widget_config.name) {
-                        widget.validate_config(widget_config).map_err(|e| {
+        for service in config.all_services() {
+            if let Some(widget_config) = &service.widget {
+                if let Some(widget) = self.get(&widget_config.name) {
+                    validate_refresh_interval(widget, service, widget_config)
+                        .and_then(|()| widget.validate_config(widget_config))
+                        .map_err(|e| {
                             VestaError::Internal(format!(
                                 "Widget '{}' validation failed for service '{}': {}",
                                 widget_config.name, service.title, e
                             ))
                         })?;
-                    } else {
-                        return Err(VestaError::Internal(format!(
-                            "Unknown widget '{}' in service '{}'",
-                            widget_config.name, service.title
-                        )));
-                    }
+                } else {
+                    return Err(VestaError::Internal(format!(
+                        "Unknown widget '{}' in service '{}'",
+                        widget_config.name, service.title
+                    )));
                 }
             }
         }
@@ -91,9 +268,15 @@ impl WidgetRegistry {
     }
 
     /// Render a widget
-    pub fn render_widget(&self, group_id: &str, service: &Service, widget: &Widget) -> Markup {
+    pub fn render_widget(
+        &self,
+        ctx: &RenderContext,
+        group_id: &str,
+        service: &Service,
+        widget: &Widget,
+    ) -> Markup {
         if let Some(handler) = self.get(&widget.name) {
-            handler.render(group_id, service)
+            handler.render(ctx, group_id, service)
         } else {
             maud::html! {
                 div class="p-4 text-red-400 bg-red-900/20 border border-red-800 rounded-xl" {
@@ -103,28 +286,81 @@ impl WidgetRegistry {
         }
     }
 
-    /// Handle a widget request
+    /// Handle a widget request, answering from the cache while the widget's TTL
+    /// lasts. A widget that fails shows its last response marked as stale, or an
+    /// error card when there is none.
+    #[tracing::instrument(skip(self, state, ctx, identity, query), fields(group = %query.group, title = %query.title))]
     pub async fn handle_widget_request(
         &self,
         widget_name: &str,
         state: Arc<AppState>,
+        ctx: &RenderContext,
+        identity: &Identity,
         query: WidgetQuery,
     ) -> Result<impl IntoResponse + use<>, VestaError> {
-        if let Some(handler) = self.get(widget_name) {
-            let markup = handler.handle_request(state, query).await?;
-            Ok(markup)
-        } else {
-            println!(
-                "widgets: {} {:?}",
-                widget_name,
-                self.get_registered_widgets()
-            );
-
-            Err(VestaError::Internal(format!(
+        let show_details = identity.is_admin();
+        let Some(handler) = self.get(widget_name) else {
+            return Err(VestaError::Internal(format!(
                 "Unknown widget: {}",
                 widget_name
-            )))
-        }
+            )));
+        };
+
+        let Some(ttl) = handler.cache_ttl() else {
+            return Ok(handler
+                .handle_request(state, ctx, query)
+                .await
+                .unwrap_or_else(|e| failed(ctx, &e, None, show_details)));
+        };
+
+        let service = state
+            .config_manager
+            .get_service(&query.group, &query.title)?
+            .ok_or_else(|| VestaError::ServiceNotFound {
+                group: query.group.clone(),
+                title: query.title.clone(),
+            })?;
+        let key = CacheKey::new(widget_name, &query, &service, ctx);
+        // Requests arriving while the widget loads wait for that load instead
+        // of each asking the upstream API; failures are not cached
+        let result = self
+            .fresh
+            .try_get_with(key.clone(), async {
+                let response = CachedResponse {
+                    markup: handler.handle_request(state, ctx, query).await?,
+                    fetched_at: Instant::now(),
+                    ttl,
+                };
+                self.last.insert(key.clone(), response.clone()).await;
+                Ok::<_, VestaError>(response)
+            })
+            .await;
+
+        Ok(match result {
+            Ok(response) => response.markup,
+            Err(e) => failed(ctx, &e, self.last.get(&key).await, show_details),
+        })
+    }
+}
+
+/// What a widget that failed shows instead. htmx only swaps successful responses,
+/// so a failure is answered with a card rather than an error status. Errors can
+/// name internal hosts and API parameters, so only administrators see them.
+fn failed(
+    ctx: &RenderContext,
+    e: &VestaError,
+    last: Option<CachedResponse>,
+    show_details: bool,
+) -> Markup {
+    tracing::warn!(error = %e, "Widget failed to load");
+    let details = if show_details {
+        e.to_string()
+    } else {
+        ctx.locale.t("widget.details_hidden").to_string()
+    };
+    match last {
+        Some(last) => widget_stale(ctx, last.markup, last.fetched_at.elapsed(), &details),
+        None => widget_error(ctx, &details),
     }
 }
 
@@ -133,3 +369,40 @@ impl Default for WidgetRegistry {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::widgets::weather_widget::WeatherWidget;
+
+    fn weather(refresh_interval: &str) -> Service {
+        toml::from_str(&format!(
+            r#"
+            title = "Weather"
+            widget = {{ name = "Weather", config = {{ refresh_interval = "{refresh_interval}" }} }}
+            "#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn out_of_range_refresh_intervals_are_clamped_instead_of_rejected() {
+        let handler = WeatherWidget::new();
+        for (value, expected) in [("7200", 3600), ("5", 60), ("300", 300)] {
+            let service = weather(value);
+            let widget = service.widget.as_ref().unwrap();
+            assert!(validate_refresh_interval(&handler, &service, widget).is_ok());
+            assert_eq!(
+                service_refresh_interval(&handler, &service),
+                Some(Duration::from_secs(expected))
+            );
+        }
+    }
+
+    #[test]
+    fn non_numeric_refresh_intervals_are_rejected() {
+        let service = weather("hourly");
+        let widget = service.widget.as_ref().unwrap();
+        assert!(validate_refresh_interval(&WeatherWidget::new(), &service, widget).is_err());
+    }
+}
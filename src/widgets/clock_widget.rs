@@ -7,6 +7,7 @@ use crate::{
     AppState,
     config::{Service, Widget},
     error::VestaResult,
+    render::RenderContext,
     widget_system::{WidgetHandler, WidgetQuery},
     widgets::widget_container,
 };
@@ -25,7 +26,7 @@ impl WidgetHandler for ClockWidget {
         "Clock"
     }
 
-    fn render(&self, _group_id: &str, service: &Service) -> Markup {
+    fn render(&self, ctx: &RenderContext, _group_id: &str, service: &Service) -> Markup {
         let now = Local::now();
         let utc_now = Utc::now();
 
@@ -36,24 +37,24 @@ impl WidgetHandler for ClockWidget {
             html! {
                 div data-clock class="text-center space-y-2" {
                     // Main time display
-                    div data-clock-time class="text-4xl font-mono font-bold text-zinc-100 tracking-tight" {
+                    div data-clock-time class="text-4xl font-mono font-bold text-surface-100 tracking-tight" {
                         (now.format("%H:%M:%S").to_string())
                     }
 
                     // Date display
-                    div data-clock-date class="text-sm text-zinc-400" {
-                        (now.format("%A, %B %d").to_string())
+                    div data-clock-date class="text-sm text-surface-400" {
+                        (ctx.locale.long_date(&now))
                     }
 
                     // Year and timezone
-                    div class="text-xs text-zinc-500 space-y-0.5" {
+                    div class="text-xs text-surface-500 space-y-0.5" {
                         div data-clock-year { (now.format("%Y").to_string()) }
                         div data-clock-tz { (now.format("%Z").to_string()) }
                     }
 
                     // UTC time
-                    div data-clock-utc class="text-xs text-zinc-600 pt-2 border-t border-zinc-800" {
-                        "UTC " (utc_now.format("%H:%M:%S").to_string())
+                    div data-clock-utc data-label=(ctx.locale.t("clock.utc")) class="text-xs text-surface-600 pt-2 border-t border-surface-800" {
+                        (ctx.locale.t("clock.utc")) " " (utc_now.format("%H:%M:%S").to_string())
                     }
                 }
             },
@@ -63,6 +64,7 @@ impl WidgetHandler for ClockWidget {
     async fn handle_request(
         &self,
         _state: Arc<AppState>,
+        ctx: &RenderContext,
         _query: WidgetQuery,
     ) -> VestaResult<Markup> {
         let now = Local::now();
@@ -71,24 +73,24 @@ impl WidgetHandler for ClockWidget {
         Ok(html! {
             div class="text-center space-y-2" {
                 // Main time display
-                div class="text-4xl font-mono font-bold text-zinc-100 tracking-tight" {
+                div class="text-4xl font-mono font-bold text-surface-100 tracking-tight" {
                     (now.format("%H:%M:%S").to_string())
                 }
 
                 // Date display
-                div class="text-sm text-zinc-400" {
-                    (now.format("%A, %B %d").to_string())
+                div class="text-sm text-surface-400" {
+                    (ctx.locale.long_date(&now))
                 }
 
                 // Year and timezone
-                div class="text-xs text-zinc-500 space-y-0.5" {
+                div class="text-xs text-surface-500 space-y-0.5" {
                     div { (now.format("%Y").to_string()) }
                     div { (now.format("%Z").to_string()) }
                 }
 
                 // UTC time
-                div class="text-xs text-zinc-600 pt-2 border-t border-zinc-800" {
-                    "UTC " (utc_now.format("%H:%M:%S").to_string())
+                div class="text-xs text-surface-600 pt-2 border-t border-surface-800" {
+                    (ctx.locale.t("clock.utc")) " " (utc_now.format("%H:%M:%S").to_string())
                 }
             }
         })
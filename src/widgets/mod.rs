@@ -4,35 +4,107 @@ pub mod sonarr_calendar_widget;
 pub mod system_stats_widget;
 pub mod weather_widget;
 
+use std::time::Duration;
+
 use maud::{Markup, html};
 
-// Helper to build Tailwind grid span classes for widgets
-pub fn grid_span_classes(width: usize, height: usize) -> String {
-    let mut classes = String::new();
-    if width > 1 {
-        classes.push_str(&format!(" col-span-{}", width));
-    }
-    if height > 1 {
-        classes.push_str(&format!(" row-span-{}", height));
-    }
-    classes
+use crate::{
+    config::{Responsive, Service},
+    render::RenderContext,
+};
+
+/// Inline CSS variables for the columns and rows a card spans, applied by the
+/// `layout-item` class in `style.css`; `None` when it spans one of each
+pub fn layout_item_style(width: Option<Responsive>, height: Option<Responsive>) -> Option<String> {
+    let variables: Vec<String> = [("width", width), ("height", height)]
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|value| value.css_variables(name, 1)))
+        .collect();
+    (!variables.is_empty()).then(|| variables.join("; "))
 }
 
-/// Reusable container for widgets with consistent styling and Tailwind grid spans
+/// Reusable container for widgets with consistent styling and grid spans
 pub fn widget_container(
-    width: Option<u8>,
-    height: Option<u8>,
+    width: Option<Responsive>,
+    height: Option<Responsive>,
     extra_classes: &str,
     content: Markup,
 ) -> Markup {
-    let w = width.unwrap_or(1) as usize;
-    let h = height.unwrap_or(1) as usize;
-    let grid_classes = grid_span_classes(w, h);
     let classes = format!(
-        "bg-zinc-900 border border-zinc-800 rounded-lg p-4 h-full{} {}",
-        grid_classes, extra_classes
+        "layout-item bg-card border border-surface-800 rounded-lg p-4 h-full {}",
+        extra_classes
     );
     html! {
-        div class=(classes) { (content) }
+        div class=(classes) style=[layout_item_style(width, height)] { (content) }
+    }
+}
+
+/// Widget container that loads the widget's content with htmx, and reloads it
+/// every `refresh_interval` when given
+pub fn widget_loader(
+    ctx: &RenderContext,
+    widget_name: &str,
+    group_id: &str,
+    service: &Service,
+    extra_classes: &str,
+    refresh_interval: Option<Duration>,
+) -> Markup {
+    let trigger = match refresh_interval {
+        Some(interval) => format!("load, every {}s", interval.as_secs()),
+        None => "load".to_string(),
+    };
+    widget_container(
+        service.width,
+        service.height,
+        extra_classes,
+        html! {
+            div
+                class="h-full"
+                hx-get=(ctx.widget_url(widget_name, group_id, &service.title))
+                hx-trigger=(trigger)
+                hx-swap="innerHTML" {
+                div class="flex items-center justify-center h-full" {
+                    div class="animate-spin rounded-full h-6 w-6 border-b-2 border-accent-500" {}
+                }
+            }
+        },
+    )
+}
+
+/// Card shown in place of a widget that failed to load, with the error on hover
+pub fn widget_error(ctx: &RenderContext, details: &str) -> Markup {
+    html! {
+        div data-widget-failed title=(details) class="flex flex-col items-center justify-center h-full gap-2 text-xs text-surface-500 font-mono text-center" {
+            svg class="w-5 h-5 text-amber-500" fill="none" stroke="currentColor" viewBox="0 0 24 24" {
+                path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M12 9v4m0 4h.01M10.3 3.9L1.8 18a2 2 0 001.7 3h17a2 2 0 001.7-3L13.7 3.9a2 2 0 00-3.4 0z" {}
+            }
+            (ctx.locale.t("widget.error"))
+            (retry_button(ctx))
+        }
+    }
+}
+
+/// The last content of a widget that failed to load, below a note of its age
+/// with the error on hover
+pub fn widget_stale(ctx: &RenderContext, content: Markup, age: Duration, details: &str) -> Markup {
+    html! {
+        div data-widget-failed {
+            div title=(details) class="flex items-center justify-between gap-2 mb-2 text-[10px] text-amber-400 font-mono" {
+                (ctx.locale.t_with("widget.stale", &[("age", &ctx.locale.duration(age))]))
+                (retry_button(ctx))
+            }
+            (content)
+        }
+    }
+}
+
+/// Loads the widget again at once; `app.js` also retries on its own, waiting
+/// longer after each failure
+fn retry_button(ctx: &RenderContext) -> Markup {
+    html! {
+        button type="button" data-widget-retry
+            class="px-2 py-0.5 rounded-md border border-surface-700 text-surface-300 hover:bg-surface-800 hover:text-surface-100 disabled:opacity-50 transition-colors duration-150" {
+            (ctx.locale.t("widget.retry"))
+        }
     }
 }
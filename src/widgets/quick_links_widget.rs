@@ -5,10 +5,11 @@ use std::sync::Arc;
 
 use crate::{
     AppState,
-    config::{Service, Widget},
+    config::Widget,
     error::{VestaError, VestaResult},
+    render::RenderContext,
+    services::icon_service::icon_source,
     widget_system::{WidgetHandler, WidgetQuery},
-    widgets::widget_container,
 };
 
 #[derive(Deserialize, Debug)]
@@ -25,8 +26,7 @@ impl QuickLinksWidget {
         Self
     }
 
-    fn parse_config(
-        &self,
+    pub fn parse_config(
         config: &std::collections::HashMap<String, String>,
     ) -> VestaResult<Vec<QuickLink>> {
         let mut links = Vec::new();
@@ -63,28 +63,14 @@ impl WidgetHandler for QuickLinksWidget {
         "QuickLinks"
     }
 
-    fn render(&self, group_id: &str, service: &Service) -> Markup {
-        widget_container(
-            service.width,
-            service.height,
-            "overflow-y-auto",
-            html! {
-                div
-                    class="h-full"
-                    hx-get=(format!("/api/widgets/QuickLinks?group={}&title={}", group_id, service.title))
-                    hx-trigger="load"
-                    hx-swap="innerHTML" {
-                    div class="flex items-center justify-center h-full" {
-                        div class="animate-spin rounded-full h-6 w-6 border-b-2 border-violet-500" {}
-                    }
-                }
-            },
-        )
+    fn container_classes(&self) -> &'static str {
+        "overflow-y-auto"
     }
 
     async fn handle_request(
         &self,
         state: Arc<AppState>,
+        ctx: &RenderContext,
         query: WidgetQuery,
     ) -> VestaResult<Markup> {
         let config_manager = &state.config_manager;
@@ -100,31 +86,31 @@ impl WidgetHandler for QuickLinksWidget {
                 VestaError::Internal("QuickLinks widget config not found".to_string())
             })?;
 
-        let links = self.parse_config(widget_config)?;
+        let links = Self::parse_config(widget_config)?;
 
         Ok(html! {
             div class="space-y-2" {
                 // Header
-                h3 class="text-xs font-semibold text-zinc-400 uppercase tracking-widest mb-3" style="font-family: 'JetBrains Mono', monospace;" { "Quick Links" }
+                h3 class="text-xs font-semibold text-surface-400 uppercase tracking-widest mb-3" { (ctx.locale.t("quick_links.title")) }
 
                 // Links
                 div class="space-y-1" {
                     @for link in &links {
                         a href=(link.url) target="_blank"
-                          class="flex items-center p-2.5 bg-zinc-800/60 hover:bg-zinc-700/60 border border-zinc-700/50 hover:border-violet-500/30 rounded-md transition-all duration-150 group cursor-pointer" {
+                          class="flex items-center p-2.5 bg-surface-800/60 hover:bg-surface-700/60 border border-surface-700/50 hover:border-accent-500/30 rounded-md transition-all duration-150 group cursor-pointer" {
                             @if let Some(icon) = &link.icon {
-                                img src=(icon) alt=(link.title) class="w-5 h-5 mr-2.5 flex-shrink-0 opacity-80" {}
+                                img src=(ctx.icon_url(&icon_source(icon, Some(&link.url)))) alt=(link.title) class="w-5 h-5 mr-2.5 flex-shrink-0 opacity-80" {}
                             } @else {
-                                div class="w-5 h-5 mr-2.5 flex-shrink-0 bg-violet-500/20 border border-violet-500/30 rounded flex items-center justify-center" {
-                                    span class="text-violet-400 text-xs font-bold font-mono" {
+                                div class="w-5 h-5 mr-2.5 flex-shrink-0 bg-accent-500/20 border border-accent-500/30 rounded flex items-center justify-center" {
+                                    span class="text-accent-400 text-xs font-bold font-mono" {
                                         (link.title.chars().next().unwrap_or('?').to_uppercase())
                                     }
                                 }
                             }
-                            span class="text-zinc-300 text-sm font-medium group-hover:text-zinc-100 transition-colors flex-1 truncate" {
+                            span class="text-surface-300 text-sm font-medium group-hover:text-surface-100 transition-colors flex-1 truncate" {
                                 (link.title)
                             }
-                            svg class="w-3.5 h-3.5 text-zinc-600 group-hover:text-zinc-400 transition-colors flex-shrink-0" fill="none" stroke="currentColor" viewBox="0 0 24 24" {
+                            svg class="w-3.5 h-3.5 text-surface-600 group-hover:text-surface-400 transition-colors flex-shrink-0" fill="none" stroke="currentColor" viewBox="0 0 24 24" {
                                 path stroke-linecap="round" stroke-linejoin="round" stroke-width="2"
                                      d="M10 6H6a2 2 0 00-2 2v10a2 2 0 002 2h10a2 2 0 002-2v-4M14 4h6m0 0v6m0-6L10 14" {}
                             }
@@ -1,5 +1,5 @@
 use async_trait::async_trait;
-use chrono::{DateTime, Duration, Local, Utc};
+use chrono::{DateTime, Duration, Local, NaiveDate, Utc};
 use indexmap::IndexMap;
 use maud::{Markup, html};
 use reqwest::Client;
@@ -8,10 +8,12 @@ use std::{collections::HashSet, sync::Arc};
 
 use crate::{
     AppState,
-    config::{Service, Widget},
+    config::Widget,
     error::{VestaError, VestaResult},
+    http_client,
+    i18n::Locale,
+    render::RenderContext,
     widget_system::{WidgetHandler, WidgetQuery},
-    widgets::widget_container,
 };
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -72,11 +74,12 @@ impl SonarrCalendarWidget {
         url: &str,
         key: &str,
     ) -> VestaResult<DownloadQueue> {
-        let response = client
-            .get(format!("{}/api/v3/queue", url))
-            .header("X-Api-Key", key)
-            .send()
-            .await?;
+        let response = http_client::send(
+            client
+                .get(format!("{}/api/v3/queue", url))
+                .header("X-Api-Key", key),
+        )
+        .await?;
 
         if !response.status().is_success() {
             return Err(VestaError::ApiError {
@@ -99,12 +102,13 @@ impl SonarrCalendarWidget {
             ("end", &day_after_tomorrow.format("%Y-%m-%d").to_string()),
         ];
 
-        let response = client
-            .get(format!("{}/api/v3/calendar", url))
-            .query(&params)
-            .header("X-Api-Key", key)
-            .send()
-            .await?;
+        let response = http_client::send(
+            client
+                .get(format!("{}/api/v3/calendar", url))
+                .query(&params)
+                .header("X-Api-Key", key),
+        )
+        .await?;
 
         if !response.status().is_success() {
             return Err(VestaError::ApiError {
@@ -134,18 +138,20 @@ impl SonarrCalendarWidget {
             })
     }
 
-    fn format_time(&self, date: &DateTime<Utc>) -> String {
-        date.with_timezone(&Local).format("%H:%M").to_string()
+    fn format_time(&self, locale: &Locale, date: &DateTime<Utc>) -> String {
+        locale.time(&date.with_timezone(&Local))
     }
 
     fn add_minutes(&self, date: &DateTime<Utc>, minutes: i64) -> DateTime<Utc> {
         *date + Duration::minutes(minutes)
     }
 
-    fn format_air_time(&self, entry: &CalendarEntry) -> String {
-        let air_date = self.format_time(&entry.air_date_utc);
-        let aired_date =
-            self.format_time(&self.add_minutes(&entry.air_date_utc, entry.series.runtime));
+    fn format_air_time(&self, locale: &Locale, entry: &CalendarEntry) -> String {
+        let air_date = self.format_time(locale, &entry.air_date_utc);
+        let aired_date = self.format_time(
+            locale,
+            &self.add_minutes(&entry.air_date_utc, entry.series.runtime),
+        );
         format!("{} - {}", air_date, aired_date)
     }
 
@@ -182,28 +188,23 @@ impl WidgetHandler for SonarrCalendarWidget {
         "SonarrCalendar"
     }
 
-    fn render(&self, group_id: &str, service: &Service) -> Markup {
-        widget_container(
-            service.width,
-            service.height,
-            "overflow-y-auto text-xs py-2 flex flex-col",
-            html! {
-                div
-                    class="h-full"
-                    hx-get=(format!("/api/widgets/{}?group={}&title={}", self.name(), group_id, service.title))
-                    hx-trigger="load"
-                    hx-swap="innerHTML" {
-                        div class="flex items-center justify-center h-full" {
-                            div class="animate-spin rounded-full h-6 w-6 border-b-2 border-violet-500" {}
-                        }
-                    }
-            },
-        )
+    fn refresh_interval(&self) -> Option<std::time::Duration> {
+        Some(std::time::Duration::from_secs(900))
+    }
+
+    // Each load asks Sonarr for the calendar and the queue
+    fn cache_ttl(&self) -> Option<std::time::Duration> {
+        Some(std::time::Duration::from_secs(300))
+    }
+
+    fn container_classes(&self) -> &'static str {
+        "overflow-y-auto text-xs py-2 flex flex-col"
     }
 
     async fn handle_request(
         &self,
         state: Arc<AppState>,
+        ctx: &RenderContext,
         query: WidgetQuery,
     ) -> VestaResult<Markup> {
         let config = state.get_config()?;
@@ -235,27 +236,23 @@ impl WidgetHandler for SonarrCalendarWidget {
         }
 
         let current_date = Utc::now();
-        let calendar_grouped: IndexMap<String, Vec<&CalendarEntry>> =
+        let calendar_grouped: IndexMap<NaiveDate, Vec<&CalendarEntry>> =
             calendar.iter().fold(IndexMap::new(), |mut acc, entry| {
-                let formatted_date = entry
-                    .air_date_utc
-                    .with_timezone(&Local)
-                    .format("%Y-%m-%d")
-                    .to_string();
-                acc.entry(formatted_date).or_default().push(entry);
+                let local_date = entry.air_date_utc.with_timezone(&Local).date_naive();
+                acc.entry(local_date).or_default().push(entry);
                 acc
             });
 
         Ok(html! {
             @if calendar_grouped.is_empty() {
                 div class="flex justify-center items-center min-w-full min-h-full text-xl font-bold" {
-                    "No entry"
+                    (ctx.locale.t("sonarr.no_entry"))
                 }
             } @else {
                 @for (date, entries) in calendar_grouped {
-                    div class="flex justify-center py-1.5 my-2 min-w-full rounded bg-zinc-800 border border-zinc-700" {
-                        a href=(format!("{}/calendar", url)) class="font-mono text-xs font-medium text-zinc-300 text-center" {
-                            (date)
+                    div class="flex justify-center py-1.5 my-2 min-w-full rounded bg-surface-800 border border-surface-700" {
+                        a href=(format!("{}/calendar", url)) class="font-mono text-xs font-medium text-surface-300 text-center" {
+                            (ctx.locale.long_date(&date))
                         }
                     }
                     @for entry in entries {
@@ -263,11 +260,11 @@ impl WidgetHandler for SonarrCalendarWidget {
                             a href=(self.format_series_url(url, &entry.series.title_slug)) class="line-clamp-1 hover:brightness-125" {
                                 (entry.series.title)
                             }
-                            span class="block text-xs text-zinc-500" {
+                            span class="block text-xs text-surface-500" {
                                 (self.format_episode(entry))
                             }
-                            span class="text-xs text-zinc-600" {
-                                (self.format_air_time(entry))
+                            span class="text-xs text-surface-600" {
+                                (self.format_air_time(&ctx.locale, entry))
                             }
                         }
                     }
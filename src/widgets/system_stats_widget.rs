@@ -1,13 +1,12 @@
 use async_trait::async_trait;
 use maud::{Markup, html};
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use crate::{
     AppState,
-    config::{Service, Widget},
-    error::{VestaError, VestaResult},
+    error::VestaResult,
+    render::RenderContext,
     widget_system::{WidgetHandler, WidgetQuery},
-    widgets::widget_container,
 };
 
 pub struct SystemStatsWidget;
@@ -20,7 +19,7 @@ impl SystemStatsWidget {
     fn render_progress_bar(&self, value: f64, max: f64, color_class: &str) -> Markup {
         let percentage = (value / max * 100.0).min(100.0);
         html! {
-            div class="w-full bg-zinc-800 rounded-full h-1.5" {
+            div class="w-full bg-surface-800 rounded-full h-1.5" {
                 div class=(format!("h-1.5 rounded-full transition-all duration-300 {}", color_class))
                      style=(format!("width: {}%", percentage)) {}
             }
@@ -42,62 +41,50 @@ impl WidgetHandler for SystemStatsWidget {
         "SystemStats"
     }
 
-    fn render(&self, group_id: &str, service: &Service) -> Markup {
-        let refresh_interval = service
-            .widget
-            .as_ref()
-            .and_then(|w| w.config.as_ref())
-            .and_then(|c| c.get("refresh_interval"))
-            .and_then(|v| v.parse::<u64>().ok())
-            .unwrap_or(5);
+    fn refresh_interval(&self) -> Option<Duration> {
+        Some(Duration::from_secs(5))
+    }
 
-        widget_container(
-            service.width,
-            service.height,
-            "overflow-y-auto",
-            html! {
-                div
-                    class="h-full"
-                    hx-get=(format!("/api/widgets/SystemStats?group={}&title={}", group_id, service.title))
-                    hx-trigger=(format!("load, every {}s", refresh_interval))
-                    hx-swap="innerHTML" {
-                    div class="flex items-center justify-center h-full" {
-                        div class="animate-spin rounded-full h-6 w-6 border-b-2 border-violet-500" {}
-                    }
-                }
-            },
-        )
+    fn container_classes(&self) -> &'static str {
+        "overflow-y-auto"
     }
 
     async fn handle_request(
         &self,
         state: Arc<AppState>,
+        ctx: &RenderContext,
         _query: WidgetQuery,
     ) -> VestaResult<Markup> {
         let stats = state.get_system_stats_service().get_snapshot().await;
+        let locale = ctx.locale;
 
         Ok(html! {
             div class="space-y-4" {
                 div class="flex items-center justify-between mb-4" {
-                    h3 class="text-sm font-semibold text-zinc-100" style="font-family: 'JetBrains Mono', monospace;" { "System Stats" }
-                    div class="text-xs text-zinc-500 font-mono" {
-                        "load " (format!("{:.2}", stats.load_avg))
+                    h3 class="text-sm font-semibold text-surface-100" { (locale.t("system_stats.title")) }
+                    div class="text-xs text-surface-500 font-mono" {
+                        (locale.t_with("system_stats.load", &[("value", &locale.number(stats.load_avg, 2))]))
                     }
                 }
 
                 div class="space-y-1.5" {
                     div class="flex justify-between items-center" {
-                        span class="text-xs text-zinc-400 uppercase tracking-wide" { "CPU" }
-                        span class="text-xs text-zinc-200 font-mono" { (format!("{:.1}%", stats.cpu_usage)) }
+                        span class="text-xs text-surface-400 uppercase tracking-wide" { (locale.t("system_stats.cpu")) }
+                        span class="text-xs text-surface-200 font-mono" { (locale.number(stats.cpu_usage.into(), 1)) "%" }
                     }
                     (self.render_progress_bar(stats.cpu_usage_percent(), 100.0, self.get_usage_color(stats.cpu_usage_percent())))
                 }
 
                 div class="space-y-1.5" {
                     div class="flex justify-between items-center" {
-                        span class="text-xs text-zinc-400 uppercase tracking-wide" { "Memory" }
-                        span class="text-xs text-zinc-200 font-mono" {
-                            (format!("{:.1}% · {}M / {}M", stats.memory_usage, stats.memory_used, stats.memory_total))
+                        span class="text-xs text-surface-400 uppercase tracking-wide" { (locale.t("system_stats.memory")) }
+                        span class="text-xs text-surface-200 font-mono" {
+                            (format!(
+                                "{}% · {}M / {}M",
+                                locale.number(stats.memory_usage, 1),
+                                locale.number(stats.memory_used as f64, 0),
+                                locale.number(stats.memory_total as f64, 0)
+                            ))
                         }
                     }
                     (self.render_progress_bar(stats.memory_usage_percent(), 100.0, self.get_usage_color(stats.memory_usage_percent())))
@@ -105,9 +92,14 @@ impl WidgetHandler for SystemStatsWidget {
 
                 div class="space-y-1.5" {
                     div class="flex justify-between items-center" {
-                        span class="text-xs text-zinc-400 uppercase tracking-wide" { "Disk (/)" }
-                        span class="text-xs text-zinc-200 font-mono" {
-                            (format!("{:.1}% · {}G / {}G", stats.disk_usage, stats.disk_used, stats.disk_total))
+                        span class="text-xs text-surface-400 uppercase tracking-wide" { (locale.t("system_stats.disk")) }
+                        span class="text-xs text-surface-200 font-mono" {
+                            (format!(
+                                "{}% · {}G / {}G",
+                                locale.number(stats.disk_usage, 1),
+                                locale.number(stats.disk_used as f64, 0),
+                                locale.number(stats.disk_total as f64, 0)
+                            ))
                         }
                     }
                     (self.render_progress_bar(stats.disk_usage_percent(), 100.0, self.get_usage_color(stats.disk_usage_percent())))
@@ -115,23 +107,4 @@ impl WidgetHandler for SystemStatsWidget {
             }
         })
     }
-
-    fn validate_config(&self, widget: &Widget) -> VestaResult<()> {
-        if let Some(config) = &widget.config
-            && let Some(interval) = config.get("refresh_interval")
-        {
-            if let Ok(interval_val) = interval.parse::<u64>() {
-                if !(1..=3600).contains(&interval_val) {
-                    return Err(VestaError::Internal(
-                        "refresh_interval must be between 1 and 3600 seconds".to_string(),
-                    ));
-                }
-            } else {
-                return Err(VestaError::Internal(
-                    "refresh_interval must be a number".to_string(),
-                ));
-            }
-        }
-        Ok(())
-    }
 }
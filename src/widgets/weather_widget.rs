@@ -1,14 +1,14 @@
 use async_trait::async_trait;
 use maud::{Markup, html};
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use crate::{
     AppState,
-    config::{Service, Widget},
+    config::Widget,
     error::{VestaError, VestaResult},
+    render::RenderContext,
     services::weather_service::WeatherConfig,
     widget_system::{WidgetHandler, WidgetQuery},
-    widgets::widget_container,
 };
 
 pub struct WeatherWidget;
@@ -25,24 +25,25 @@ impl WeatherWidget {
         }
     }
 
+    /// Catalog key of the description, e.g. `weather.clear_sky`
     fn get_weather_description(&self, weather_code: u32) -> &'static str {
         match weather_code {
-            0 => "Clear sky",
-            1 => "Mainly clear",
-            2 => "Partly cloudy",
-            3 => "Overcast",
-            45 | 48 => "Fog",
-            51 | 53 | 55 => "Drizzle",
-            56 | 57 => "Freezing drizzle",
-            61 | 63 | 65 => "Rain",
-            66 | 67 => "Freezing rain",
-            71 | 73 | 75 => "Snow",
-            77 => "Snow grains",
-            80..=82 => "Rain showers",
-            85 | 86 => "Snow showers",
-            95 => "Thunderstorm",
-            96 | 99 => "Thunderstorm with hail",
-            _ => "Unknown",
+            0 => "weather.clear_sky",
+            1 => "weather.mainly_clear",
+            2 => "weather.partly_cloudy",
+            3 => "weather.overcast",
+            45 | 48 => "weather.fog",
+            51 | 53 | 55 => "weather.drizzle",
+            56 | 57 => "weather.freezing_drizzle",
+            61 | 63 | 65 => "weather.rain",
+            66 | 67 => "weather.freezing_rain",
+            71 | 73 | 75 => "weather.snow",
+            77 => "weather.snow_grains",
+            80..=82 => "weather.rain_showers",
+            85 | 86 => "weather.snow_showers",
+            95 => "weather.thunderstorm",
+            96 | 99 => "weather.thunderstorm_hail",
+            _ => "weather.unknown",
         }
     }
 
@@ -67,17 +68,18 @@ impl WeatherWidget {
         }
     }
 
-    fn get_wind_direction(&self, degrees: u32) -> &'static str {
+    /// Index of the compass point in `weather.directions`, starting at north
+    fn get_wind_direction(&self, degrees: u32) -> usize {
         match degrees {
-            0..=22 | 338..=360 => "N",
-            23..=67 => "NE",
-            68..=112 => "E",
-            113..=157 => "SE",
-            158..=202 => "S",
-            203..=247 => "SW",
-            248..=292 => "W",
-            293..=337 => "NW",
-            _ => "N",
+            0..=22 | 338..=360 => 0,
+            23..=67 => 1,
+            68..=112 => 2,
+            113..=157 => 3,
+            158..=202 => 4,
+            203..=247 => 5,
+            248..=292 => 6,
+            293..=337 => 7,
+            _ => 0,
         }
     }
 }
@@ -88,36 +90,19 @@ impl WidgetHandler for WeatherWidget {
         "Weather"
     }
 
-    fn render(&self, group_id: &str, service: &Service) -> Markup {
-        let refresh_interval = service
-            .widget
-            .as_ref()
-            .and_then(|w| w.config.as_ref())
-            .and_then(|c| c.get("refresh_interval"))
-            .and_then(|v| v.parse::<u64>().ok())
-            .unwrap_or(600);
+    fn refresh_interval(&self) -> Option<Duration> {
+        Some(Duration::from_secs(600))
+    }
 
-        widget_container(
-            service.width,
-            service.height,
-            "",
-            html! {
-                div
-                    class="h-full"
-                    hx-get=(format!("/api/widgets/Weather?group={}&title={}", group_id, service.title))
-                    hx-trigger=(format!("load, every {}s", refresh_interval))
-                    hx-swap="innerHTML" {
-                    div class="flex items-center justify-center h-full" {
-                        div class="animate-spin rounded-full h-6 w-6 border-b-2 border-violet-500" {}
-                    }
-                }
-            },
-        )
+    // Open-Meteo updates its forecasts every 15 minutes at most
+    fn min_refresh_interval(&self) -> Duration {
+        Duration::from_secs(60)
     }
 
     async fn handle_request(
         &self,
         state: Arc<AppState>,
+        ctx: &RenderContext,
         query: WidgetQuery,
     ) -> VestaResult<Markup> {
         let config_manager = &state.config_manager;
@@ -156,30 +141,41 @@ impl WidgetHandler for WeatherWidget {
             .await?;
 
         let temp_unit = self.get_temperature_unit(&weather_config.units);
-        let weather_description = self.get_weather_description(weather_data.current.weather_code);
+        let locale = ctx.locale;
+        let weather_description =
+            locale.t(self.get_weather_description(weather_data.current.weather_code));
         let weather_icon = self.get_weather_icon(weather_data.current.weather_code);
-        let wind_direction = self.get_wind_direction(weather_data.current.wind_direction_10m);
+        let wind_direction_key = format!(
+            "weather.directions.{}",
+            self.get_wind_direction(weather_data.current.wind_direction_10m)
+        );
+        let wind_direction = locale.t(&wind_direction_key);
+        let feels = format!(
+            "{}{}",
+            locale.number(weather_data.current.apparent_temperature, 0),
+            temp_unit
+        );
 
         Ok(html! {
             div class="space-y-4" {
                 div class="text-center" {
-                    h3 class="text-sm font-semibold text-zinc-100" style="font-family: 'JetBrains Mono', monospace;" {
-                        "Weather"
+                    h3 class="text-sm font-semibold text-surface-100" {
+                        (locale.t("weather.title"))
                     }
-                    div class="text-xs text-zinc-500 font-mono" {
+                    div class="text-xs text-surface-500 font-mono" {
                         (format!("{}°, {}°", weather_config.latitude, weather_config.longitude))
                     }
                 }
 
                 div class="flex items-center justify-between" {
                     div class="flex-1" {
-                        div class="text-3xl font-bold text-zinc-100 mb-1 font-mono" {
-                            (format!("{:.0}{}", weather_data.current.temperature_2m, temp_unit))
+                        div class="text-3xl font-bold text-surface-100 mb-1 font-mono" {
+                            (locale.number(weather_data.current.temperature_2m, 0)) (temp_unit)
                         }
-                        div class="text-xs text-zinc-500" {
-                            "Feels " (format!("{:.0}{}", weather_data.current.apparent_temperature, temp_unit))
+                        div class="text-xs text-surface-500" {
+                            (locale.t_with("weather.feels", &[("temperature", &feels)]))
                         }
-                        div class="text-sm text-zinc-400 capitalize mt-1" {
+                        div class="text-sm text-surface-400 capitalize mt-1" {
                             (weather_description)
                         }
                     }
@@ -188,15 +184,15 @@ impl WidgetHandler for WeatherWidget {
                     }
                 }
 
-                div class="grid grid-cols-2 gap-3 pt-3 border-t border-zinc-800" {
+                div class="grid grid-cols-2 gap-3 pt-3 border-t border-surface-800" {
                     div class="text-center" {
-                        div class="text-xs text-zinc-500 uppercase tracking-wide" { "Humidity" }
-                        div class="text-sm font-semibold text-zinc-200 font-mono" { (weather_data.current.relative_humidity_2m) "%" }
+                        div class="text-xs text-surface-500 uppercase tracking-wide" { (locale.t("weather.humidity")) }
+                        div class="text-sm font-semibold text-surface-200 font-mono" { (weather_data.current.relative_humidity_2m) "%" }
                     }
                     div class="text-center" {
-                        div class="text-xs text-zinc-500 uppercase tracking-wide" { "Wind" }
-                        div class="text-sm font-semibold text-zinc-200 font-mono" {
-                            (format!("{:.0} {}", weather_data.current.wind_speed_10m, wind_direction))
+                        div class="text-xs text-surface-500 uppercase tracking-wide" { (locale.t("weather.wind")) }
+                        div class="text-sm font-semibold text-surface-200 font-mono" {
+                            (locale.number(weather_data.current.wind_speed_10m, 0)) " " (wind_direction)
                         }
                     }
                 }
@@ -254,15 +250,6 @@ impl WidgetHandler for WeatherWidget {
             ));
         }
 
-        if let Some(interval_str) = config.get("refresh_interval")
-            && let Ok(interval) = interval_str.parse::<u64>()
-            && !(60..=3600).contains(&interval)
-        {
-            return Err(VestaError::Internal(
-                "refresh_interval must be between 60 and 3600 seconds".to_string(),
-            ));
-        }
-
         Ok(())
     }
 }
@@ -1,5 +1,6 @@
+// In the language of the page, as the server rendered it
 function formatDate(d) {
-	return d.toLocaleDateString(undefined, {
+	return d.toLocaleDateString(document.documentElement.lang || undefined, {
 		weekday: "long",
 		month: "long",
 		day: "2-digit",
@@ -33,7 +34,7 @@ function initClock(root) {
 		if (tzEl)
 			tzEl.textContent = Intl.DateTimeFormat().resolvedOptions().timeZone || "";
 		if (utcEl) {
-			const label = "UTC: ";
+			const label = `${utcEl.dataset.label || "UTC"}: `;
 			utcEl.textContent = label + formatUTCTime(now);
 		}
 	}
@@ -99,3 +100,350 @@ document.addEventListener("DOMContentLoaded", () => {
 		});
 	}
 });
+
+const SEARCH_DEBOUNCE_MS = 120;
+
+function searchResultIcon(result) {
+	if (result.icon) {
+		const img = document.createElement("img");
+		img.src = result.icon;
+		img.alt = "";
+		img.className = "w-5 h-5 object-contain flex-shrink-0";
+		return img;
+	}
+
+	const placeholder = document.createElement("span");
+	placeholder.className =
+		"w-5 h-5 flex-shrink-0 rounded bg-accent-500/20 border border-accent-500/30 text-accent-400 text-xs font-bold font-mono flex items-center justify-center";
+	// Web searches show the first letter of the provider
+	const name = result.kind === "web" ? result.subtitle : result.title;
+	placeholder.textContent = name.charAt(0).toUpperCase();
+	return placeholder;
+}
+
+function initCommandPalette() {
+	const palette = document.getElementById("command-palette");
+	if (!palette) return;
+	const input = document.getElementById("command-palette-input");
+	const list = document.getElementById("command-palette-results");
+	const openButton = document.getElementById("command-palette-open");
+	const searchUrl = palette.dataset.searchUrl;
+
+	let results = [];
+	let selected = 0;
+	let pending = null;
+	let debounce = null;
+
+	function render() {
+		list.replaceChildren(
+			...results.map((result, index) => {
+				const item = document.createElement("li");
+				item.setAttribute("role", "option");
+				item.setAttribute("aria-selected", String(index === selected));
+				item.className =
+					index === selected
+						? "flex items-center gap-3 px-4 py-2 cursor-pointer bg-surface-800/80"
+						: "flex items-center gap-3 px-4 py-2 cursor-pointer hover:bg-surface-800/40";
+
+				const text = document.createElement("div");
+				text.className = "flex-1 min-w-0";
+				const title = document.createElement("p");
+				title.className = "text-sm text-surface-100 truncate";
+				title.textContent =
+					result.kind === "web" ? `Search for "${result.title}"` : result.title;
+				const subtitle = document.createElement("p");
+				subtitle.className = "text-xs text-surface-500 truncate";
+				subtitle.textContent = result.subtitle;
+				text.append(title, subtitle);
+
+				item.append(searchResultIcon(result), text);
+				item.addEventListener("mousemove", () => {
+					if (selected !== index) {
+						selected = index;
+						render();
+					}
+				});
+				item.addEventListener("click", () => openResult(result));
+				return item;
+			}),
+		);
+		list.children[selected]?.scrollIntoView({ block: "nearest" });
+	}
+
+	async function runSearch(query) {
+		pending?.abort();
+		pending = new AbortController();
+		try {
+			const url = `${searchUrl}?q=${encodeURIComponent(query)}`;
+			const response = await fetch(url, {
+				signal: pending.signal,
+				headers: { Accept: "application/json" },
+			});
+			const body = await response.json();
+			results = body.status === "success" ? body.data.results : [];
+		} catch (error) {
+			if (error.name === "AbortError") return;
+			results = [];
+		}
+		selected = 0;
+		render();
+	}
+
+	function openResult(result) {
+		closePalette();
+		window.open(result.url, "_blank", "noopener");
+	}
+
+	function openPalette() {
+		palette.hidden = false;
+		input.value = "";
+		results = [];
+		render();
+		input.focus();
+	}
+
+	function closePalette() {
+		palette.hidden = true;
+		pending?.abort();
+	}
+
+	input.addEventListener("input", () => {
+		clearTimeout(debounce);
+		const query = input.value.trim();
+		if (!query) {
+			pending?.abort();
+			results = [];
+			render();
+			return;
+		}
+		debounce = setTimeout(() => runSearch(query), SEARCH_DEBOUNCE_MS);
+	});
+
+	input.addEventListener("keydown", (event) => {
+		if (event.key === "ArrowDown" && results.length) {
+			event.preventDefault();
+			selected = (selected + 1) % results.length;
+			render();
+		} else if (event.key === "ArrowUp" && results.length) {
+			event.preventDefault();
+			selected = (selected - 1 + results.length) % results.length;
+			render();
+		} else if (event.key === "Enter" && results[selected]) {
+			event.preventDefault();
+			openResult(results[selected]);
+		}
+	});
+
+	palette.addEventListener("click", (event) => {
+		if (event.target === palette) closePalette();
+	});
+
+	if (openButton) {
+		openButton.addEventListener("click", openPalette);
+	}
+
+	document.addEventListener("keydown", (event) => {
+		const typing =
+			event.target instanceof HTMLElement &&
+			(event.target.isContentEditable ||
+				["INPUT", "TEXTAREA", "SELECT"].includes(event.target.tagName));
+
+		if ((event.ctrlKey || event.metaKey) && event.key.toLowerCase() === "k") {
+			event.preventDefault();
+			palette.hidden ? openPalette() : closePalette();
+		} else if (event.key === "/" && !typing && palette.hidden) {
+			event.preventDefault();
+			openPalette();
+		} else if (event.key === "Escape" && !palette.hidden) {
+			closePalette();
+		}
+	});
+}
+
+document.addEventListener("DOMContentLoaded", initCommandPalette);
+
+function initTagFilter() {
+	const chips = document.querySelectorAll("[data-tag-filter]");
+	let active = null;
+
+	function apply() {
+		document.querySelectorAll("[data-service]").forEach((service) => {
+			const tags = service.dataset.tags.split(" ");
+			service.hidden = active !== null && !tags.includes(active);
+		});
+		// Groups left without a visible service are hidden as well
+		document.querySelectorAll("[data-group]").forEach((group) => {
+			group.hidden = !group.querySelector("[data-service]:not([hidden])");
+		});
+		chips.forEach((chip) => {
+			chip.setAttribute("aria-pressed", String(chip.dataset.tagFilter === active));
+		});
+	}
+
+	chips.forEach((chip) => {
+		chip.addEventListener("click", () => {
+			const tag = chip.dataset.tagFilter;
+			active = active === tag ? null : tag;
+			apply();
+		});
+	});
+}
+
+document.addEventListener("DOMContentLoaded", initTagFilter);
+
+const COLLAPSE_STORAGE_KEY = "vesta:open-groups";
+
+function readGroupState() {
+	try {
+		return JSON.parse(localStorage.getItem(COLLAPSE_STORAGE_KEY)) || {};
+	} catch {
+		return {};
+	}
+}
+
+function initGroupCollapse() {
+	const groups = document.querySelectorAll("details[data-group]");
+	const state = readGroupState();
+
+	// Groups the browser has not seen yet keep the default from the config
+	groups.forEach((group) => {
+		if (group.id in state) group.open = state[group.id];
+	});
+
+	groups.forEach((group) => {
+		group.addEventListener("toggle", () => {
+			const current = readGroupState();
+			current[group.id] = group.open;
+			try {
+				localStorage.setItem(COLLAPSE_STORAGE_KEY, JSON.stringify(current));
+			} catch {
+				// Storage may be unavailable, e.g. in private windows
+			}
+		});
+	});
+
+	// Jumping to a group from the sidebar opens it and the groups around it
+	function openTarget() {
+		const id = decodeURIComponent(location.hash.slice(1));
+		let group = id ? document.getElementById(id) : null;
+		while (group) {
+			if (group.matches("details[data-group]")) group.open = true;
+			group = group.parentElement;
+		}
+	}
+
+	openTarget();
+	window.addEventListener("hashchange", openTarget);
+}
+
+document.addEventListener("DOMContentLoaded", initGroupCollapse);
+
+function initServiceActions() {
+	document.querySelectorAll("[data-action-url]").forEach((button) => {
+		button.addEventListener("click", async () => {
+			button.disabled = true;
+			delete button.dataset.result;
+			try {
+				const response = await fetch(button.dataset.actionUrl, {
+					method: "POST",
+					// Vesta rejects state-changing requests other sites could make
+					headers: { "HX-Request": "true" },
+				});
+				const body = await response.json();
+				button.dataset.result = response.ok ? "ok" : "error";
+				button.title = (response.ok ? body.data?.message : body.details) || "";
+			} catch (error) {
+				button.dataset.result = "error";
+				button.title = String(error);
+			} finally {
+				button.disabled = false;
+			}
+		});
+	});
+}
+
+document.addEventListener("DOMContentLoaded", initServiceActions);
+
+function initOffline() {
+	const root = document.documentElement;
+	if ("serviceWorker" in navigator && root.dataset.serviceWorker) {
+		navigator.serviceWorker
+			.register(root.dataset.serviceWorker, {
+				scope: root.dataset.serviceWorkerScope,
+			})
+			.catch(() => {
+				// Service workers need HTTPS; the dashboard works without one
+			});
+	}
+
+	const template = document.getElementById("offline-indicator");
+	if (!template || !window.htmx) return;
+
+	// Widgets that fail to load show as offline instead of spinning forever
+	document.body.addEventListener("htmx:sendError", (event) => {
+		const elt = event.detail.elt;
+		if (elt.getAttribute("hx-swap") !== "innerHTML") return;
+		elt.replaceChildren(template.content.cloneNode(true));
+	});
+
+	// and load again once the connection is back
+	window.addEventListener("online", () => {
+		document.querySelectorAll("[data-offline]").forEach((indicator) => {
+			const elt = indicator.closest("[hx-get]");
+			if (elt) {
+				htmx.ajax("GET", elt.getAttribute("hx-get"), {
+					source: elt,
+					target: elt,
+					swap: "innerHTML",
+				});
+			}
+		});
+	});
+}
+
+document.addEventListener("DOMContentLoaded", initOffline);
+
+const WIDGET_RETRY_BASE_MS = 5000;
+const WIDGET_RETRY_MAX_MS = 5 * 60 * 1000;
+
+// Widgets that fail to load are tried again sooner than their refresh interval,
+// waiting twice as long after each failure; their retry button tries at once
+function initWidgetRetry() {
+	if (!window.htmx) return;
+	const attempts = new WeakMap();
+	const timers = new WeakMap();
+
+	function retry(elt) {
+		clearTimeout(timers.get(elt));
+		htmx.ajax("GET", elt.getAttribute("hx-get"), {
+			source: elt,
+			target: elt,
+			swap: "innerHTML",
+		});
+	}
+
+	document.body.addEventListener("htmx:afterSwap", (event) => {
+		const elt = event.detail.target;
+		if (!elt.hasAttribute("hx-get")) return;
+		clearTimeout(timers.get(elt));
+		if (!elt.querySelector(":scope > [data-widget-failed]")) {
+			attempts.delete(elt);
+			return;
+		}
+
+		const attempt = attempts.get(elt) || 0;
+		attempts.set(elt, attempt + 1);
+		const delay = Math.min(WIDGET_RETRY_BASE_MS * 2 ** attempt, WIDGET_RETRY_MAX_MS);
+		timers.set(elt, setTimeout(() => retry(elt), delay));
+	});
+
+	document.body.addEventListener("click", (event) => {
+		const button = event.target.closest("[data-widget-retry]");
+		const elt = button?.closest("[hx-get]");
+		if (!elt) return;
+		button.disabled = true;
+		retry(elt);
+	});
+}
+
+document.addEventListener("DOMContentLoaded", initWidgetRetry);
@@ -0,0 +1,77 @@
+// Served at `/sw.js`, after a prelude that defines SHELL_URLS, DASHBOARD_URL,
+// LOGOUT_URL and CACHE_VERSION for this deployment.
+
+const SHELL_CACHE = `vesta-shell-${CACHE_VERSION}`;
+const PAGE_CACHE = "vesta-pages";
+
+self.addEventListener("install", (event) => {
+	event.waitUntil(
+		caches
+			.open(SHELL_CACHE)
+			// One missing asset should not keep the rest from being cached
+			.then((cache) => Promise.all(SHELL_URLS.map((url) => cache.add(url).catch(() => {}))))
+			.then(() => self.skipWaiting()),
+	);
+});
+
+self.addEventListener("activate", (event) => {
+	event.waitUntil(
+		caches
+			.keys()
+			.then((keys) =>
+				Promise.all(
+					keys
+						.filter((key) => key.startsWith("vesta-shell-") && key !== SHELL_CACHE)
+						.map((key) => caches.delete(key)),
+				),
+			)
+			.then(() => self.clients.claim()),
+	);
+});
+
+function isDashboard(url) {
+	return url.pathname === new URL(DASHBOARD_URL, self.location).pathname;
+}
+
+// The last dashboard seen, shown when the server cannot be reached
+async function dashboard(request) {
+	try {
+		const response = await fetch(request);
+		if (response.ok && !response.redirected) {
+			const cache = await caches.open(PAGE_CACHE);
+			await cache.put(DASHBOARD_URL, response.clone());
+		}
+		return response;
+	} catch (error) {
+		const cached = await caches.match(DASHBOARD_URL, { cacheName: PAGE_CACHE });
+		if (cached) return cached;
+		throw error;
+	}
+}
+
+// Versioned assets never change, so the cached copy is always right
+async function shell(request) {
+	const cached = await caches.match(request, { cacheName: SHELL_CACHE });
+	return cached || fetch(request);
+}
+
+self.addEventListener("fetch", (event) => {
+	const request = event.request;
+	const url = new URL(request.url);
+	if (url.origin !== self.location.origin) return;
+
+	// Whoever signs in next must not see the previous user's dashboard
+	if (request.method === "POST" && url.pathname === new URL(LOGOUT_URL, self.location).pathname) {
+		event.waitUntil(caches.delete(PAGE_CACHE));
+		return;
+	}
+	if (request.method !== "GET") return;
+
+	if (request.mode === "navigate" && isDashboard(url)) {
+		event.respondWith(dashboard(request));
+	} else if (SHELL_URLS.includes(url.pathname)) {
+		event.respondWith(shell(request));
+	}
+	// Everything else, widgets included, goes to the network; app.js shows
+	// widgets that cannot be loaded as offline
+});